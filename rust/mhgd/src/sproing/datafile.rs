@@ -13,17 +13,30 @@ use godot::engine::{ResourceFormatLoader, ResourceSaver};
 use godot::engine::{ResourceFormatLoaderVirtual, ResourceLoader};
 use godot::prelude::*;
 use itertools::Itertools;
-use springylib::archive::Archive;
+use springylib::archive::path::normalize;
+use springylib::archive::reader::ArchiveReader;
+use springylib::media::rle::RleImage;
+use springylib::media::sprites::Sprites;
+use springylib::media::transparency::Transparency;
+use springylib::vfs::directory::Directory;
+use springylib::vfs::Vfs;
 use springylib::DatafileFile;
+use std::collections::HashMap;
 use std::fs::File;
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::Mutex;
 
 const DAT_PATH: &str = "../games/Schatzjäger/data/datafile.dat";
+/// Loose files in here override the ones in the datafile
+const MOD_PATH: &str = "../games/Schatzjäger/mods";
 
 #[derive(GodotClass)]
 #[class(base=ResourceFormatLoader)]
 pub struct DatafileLoader {
-    pub datafile_table: Archive,
+    pub datafile: Mutex<Vfs>,
+    /// How rle sprites and bitmaps are keyed
+    pub transparency: Transparency,
 
     #[base]
     pub base: Base<ResourceFormatLoader>,
@@ -32,7 +45,7 @@ pub struct DatafileLoader {
 fn convert_path(path: &GodotString) -> String {
     path.to_string()
         .strip_prefix("datafile://")
-        .map(|it| it.replace('/', "\\"))
+        .map(normalize)
         .expect("Invalid path")
 }
 
@@ -48,6 +61,27 @@ impl DatafileLoader {
         ResourceSaver::singleton().save(resource, cache_path.into(), SaverFlags::FLAG_NONE);
     }
 
+    /// Loads the rle files of a sprite set by their file name
+    fn load_rle_images(&self, path: &str, sprites: &[Sprites]) -> HashMap<String, RleImage> {
+        let dir = path.strip_suffix("sprites.txt").unwrap_or(path);
+        let mut datafile = self.datafile.lock().unwrap();
+        let mut images = HashMap::new();
+        for sprite in sprites {
+            let rle_path = format!("{}sprites/{}.rle", dir, sprite.file_name);
+            if !datafile.exists(&rle_path) {
+                continue;
+            }
+            match datafile.load(&rle_path) {
+                Ok(DatafileFile::RleSprite(rle)) => {
+                    images.insert(sprite.file_name.clone(), *rle);
+                }
+                Ok(_) => (),
+                Err(err) => printerr(format!("{}: {}", rle_path, err).to_variant(), &[]),
+            }
+        }
+        images
+    }
+
     fn get_cache_path(&self, path: String) -> String {
         format!(
             "{}/.cache/{}",
@@ -55,7 +89,7 @@ impl DatafileLoader {
                 .replace('\\', "/")
                 .strip_suffix("datafile.dat")
                 .unwrap(),
-            path.replace('\\', "/")
+            path
         )
     }
 
@@ -81,12 +115,17 @@ impl DatafileLoader {
 #[godot_api]
 impl ResourceFormatLoaderVirtual for DatafileLoader {
     fn init(base: Base<Self::Base>) -> Self {
-        let mut file = File::open(DAT_PATH).unwrap();
-        let datafile_table = Archive::read(&mut file).unwrap();
+        let mut vfs = Vfs::new();
+        vfs.mount(ArchiveReader::new(File::open(DAT_PATH).unwrap()).unwrap());
+        if Path::new(MOD_PATH).is_dir() {
+            vfs.mount(Directory::new(MOD_PATH).unwrap());
+        }
+        let datafile = Mutex::new(vfs);
 
         DatafileLoader {
             base,
-            datafile_table,
+            datafile,
+            transparency: Transparency::default(),
         }
     }
 
@@ -117,8 +156,10 @@ impl ResourceFormatLoaderVirtual for DatafileLoader {
     }
 
     fn exists(&self, path: GodotString) -> bool {
-        self.datafile_table
-            .contains_key(convert_path(&path).as_str())
+        self.datafile
+            .lock()
+            .unwrap()
+            .exists(convert_path(&path).as_str())
     }
 
     fn get_classes_used(&self, _path: GodotString) -> PackedStringArray {
@@ -145,12 +186,19 @@ impl ResourceFormatLoaderVirtual for DatafileLoader {
             return resource.to_variant();
         }
 
-        if let Some(target) = self.datafile_table.get(datafile_path.as_str()) {
-            let mut file = File::open(DAT_PATH).unwrap();
-            match target.load_from(&mut file) {
+        // don't hold the lock while converting, loading a level recursively loads its tiles
+        let loaded = {
+            let mut datafile = self.datafile.lock().unwrap();
+            datafile
+                .exists(datafile_path.as_str())
+                .then(|| datafile.load(datafile_path.as_str()))
+        };
+
+        if let Some(loaded) = loaded {
+            match loaded {
                 Ok(DatafileFile::Level(level)) => {
                     let level_id = datafile_path
-                        .split_terminator('\\')
+                        .split_terminator('/')
                         .find(|i| i.starts_with("level"))
                         .map(|lvl| u32::from_str(lvl.strip_prefix("level").unwrap()).unwrap())
                         .unwrap();
@@ -159,14 +207,19 @@ impl ResourceFormatLoaderVirtual for DatafileLoader {
                     self.save_to_cache(tile_map.share().upcast(), format!("{}.scn", datafile_path));
                     tile_map.to_variant()
                 }
-                Ok(DatafileFile::Txt(txt)) => {
-                    let game_object = parse_game_object(txt);
-                    self.save_to_cache(
-                        game_object.share().upcast(),
-                        format!("{}.res", datafile_path),
-                    );
-                    game_object.to_variant()
-                }
+                Ok(DatafileFile::Txt(txt)) => match parse_game_object(txt) {
+                    Ok(game_object) => {
+                        self.save_to_cache(
+                            game_object.share().upcast(),
+                            format!("{}.res", datafile_path),
+                        );
+                        game_object.to_variant()
+                    }
+                    Err(err) => {
+                        printerr(err.to_string().to_variant(), &[]);
+                        Error::ERR_FILE_CORRUPT.to_variant()
+                    }
+                },
                 Ok(DatafileFile::Ui(ui)) => {
                     let full_path = virtual_path.to_string();
                     let (_, _, base_path) = full_path
@@ -184,7 +237,7 @@ impl ResourceFormatLoaderVirtual for DatafileLoader {
                 }
                 Ok(DatafileFile::Translations(translations)) => {
                     let mut translation = Translation::new();
-                    for (key, message) in translations {
+                    for (key, message) in translations.locale(0).into_iter().flatten() {
                         translation.add_message(
                             format!("%{}%", key).into(),
                             message.join("\n").into(),
@@ -208,9 +261,30 @@ impl ResourceFormatLoaderVirtual for DatafileLoader {
                     audio.set_packet_sequence(packet);
                     audio.to_variant()
                 }
-                Ok(DatafileFile::RleSprite(rle)) => load_rle_as_sprite_frames(*rle).to_variant(),
+                Ok(DatafileFile::RleSprite(rle)) => {
+                    match load_rle_as_sprite_frames(*rle, self.transparency.rle) {
+                        Ok(frames) => frames.to_variant(),
+                        Err(err) => {
+                            printerr(err.to_string().to_variant(), &[]);
+                            Error::ERR_FILE_CORRUPT.to_variant()
+                        }
+                    }
+                }
                 Ok(DatafileFile::Sprites(sprites)) => {
-                    let sprite_frames = load_sprite_frames(sprites, virtual_path);
+                    let issues = {
+                        let mut datafile = self.datafile.lock().unwrap();
+                        Sprites::validate(&sprites, &datafile_path, &mut datafile)
+                    };
+                    for issue in issues {
+                        godot_warn!("{}: {}", datafile_path, issue);
+                    }
+                    let rle_images = self.load_rle_images(&datafile_path, &sprites);
+                    let sprite_frames = load_sprite_frames(
+                        sprites,
+                        virtual_path,
+                        &rle_images,
+                        self.transparency.rle,
+                    );
 
                     self.save_to_cache(
                         sprite_frames.share().upcast(),
@@ -219,12 +293,16 @@ impl ResourceFormatLoaderVirtual for DatafileLoader {
                     sprite_frames.to_variant()
                 }
                 Ok(DatafileFile::Bitmap(data)) => {
-                    let gd_image = match load_bmp_as_image_texture(data) {
+                    let transparency = self.transparency.bmp;
+                    let gd_image = match load_bmp_as_image_texture(data, transparency) {
                         Ok(image) => image,
-                        Err(err) => return err.to_variant(),
+                        Err(err) => {
+                            printerr(err.to_string().to_variant(), &[]);
+                            return Error::ERR_FILE_CORRUPT.to_variant();
+                        }
                     };
 
-                    if datafile_path.contains("\\fonts\\") {
+                    if datafile_path.contains("/fonts/") {
                         panic!();
                         /*let font = load_bitmap_font(gd_image);
 
@@ -246,11 +324,7 @@ impl ResourceFormatLoaderVirtual for DatafileLoader {
                 }
                 Ok(DatafileFile::TileCollision(collision)) => {
                     let tile_collision = Gd::new(TileCollision {
-                        collision: collision
-                            .chars()
-                            .filter_map(|c| c.to_digit(10))
-                            .map(|d| d as u8)
-                            .collect(),
+                        collision: collision.tiles.iter().map(|flags| flags.0).collect(),
                     });
 
                     // No need to save this to cache, we only use this internally
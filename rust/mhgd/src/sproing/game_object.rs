@@ -1,7 +1,9 @@
 use godot::engine::Resource;
+use godot::log::godot_warn;
 use godot::prelude::*;
-use itertools::Itertools;
-use std::str::FromStr;
+use springylib::error::Error;
+use springylib::media::object_script;
+use springylib::media::object_script::{ObjectNode, PropValue};
 
 #[derive(GodotClass)]
 #[class(base=Resource, init)]
@@ -37,100 +39,61 @@ pub struct ObjectData {
 #[godot_api]
 impl ObjectData {}
 
-pub fn parse_game_object(contents: String) -> Gd<ObjectScript> {
-    Gd::<ObjectScript>::with_base(|base| {
+pub fn parse_game_object(contents: String) -> Result<Gd<ObjectScript>, Error> {
+    let script = object_script::ObjectScript::parse(contents.as_str())?;
+    for (number, line) in script.skipped.iter() {
+        godot_warn!("Skipped line {} of object script: {}", number, line);
+    }
+
+    Ok(Gd::<ObjectScript>::with_base(|base| {
         let mut object_script = ObjectScript {
             dynamic_objects: Array::new(),
             static_objects: Array::new(),
             base,
         };
 
-        let mut lines = contents
-            .lines()
-            .map(|l| l.trim())
-            .filter(|l| !l.is_empty())
-            .filter(|l| !l.starts_with('#'));
-
-        while let Some(line) = lines.next() {
-            match line {
-                "DYNAMIC OBJECT START" => {
-                    object_script.dynamic_objects.push(read_object(&mut lines))
-                }
-                "OBJECT START" => object_script.static_objects.push(read_object(&mut lines)),
-                l => eprintln!("TODO: {}", l),
-            };
+        for object in script.objects.iter() {
+            if object.dynamic {
+                object_script.dynamic_objects.push(convert_object(object));
+            } else {
+                object_script.static_objects.push(convert_object(object));
+            }
         }
 
         object_script
-    })
+    }))
 }
 
-pub fn read_object<'s, I>(lines: &mut I) -> Gd<ObjectData>
-where
-    I: Iterator<Item = &'s str>,
-{
-    let class_type = lines
-        .next()
-        .unwrap()
-        .strip_prefix("class type:")
-        .unwrap()
-        .trim()
-        .trim_matches('"');
-    let (resource_type, name) = lines
-        .next()
-        .unwrap()
-        .splitn(2, ']')
-        .map(|x| x.trim())
-        .collect_tuple::<(&str, &str)>()
-        .unwrap();
-
+pub fn convert_object(object: &ObjectNode) -> Gd<ObjectData> {
     Gd::<ObjectData>::with_base(|base| {
         let mut object_data = ObjectData {
-            class_type: class_type.into(),
-            resource_type: resource_type
-                .trim_start_matches('[')
-                .trim_end_matches(']')
-                .into(),
-            name: name.trim_matches('"').into(),
+            class_type: object.class_type.as_str().into(),
+            resource_type: object.resource_type.as_str().into(),
+            name: object.name.as_str().into(),
             props: Dictionary::new(),
             children: Array::new(),
             base,
         };
 
-        lines.next();
-        loop {
-            match lines.next().unwrap() {
-                "}" => break,
-                l => {
-                    let (_, key, value) = l
-                        .splitn(3, '"')
-                        .map(|x| x.trim())
-                        .collect_tuple::<(&str, &str, &str)>()
-                        .unwrap();
-                    let values = value
-                        .split_whitespace()
-                        .map(|s| f32::from_str(s).unwrap())
-                        .collect_vec();
-                    object_data.props.insert(
-                        key,
-                        match values.len() {
-                            1 => values[0].to_variant(),
-                            2 => Vector2 {
-                                x: values[0],
-                                y: values[1],
-                            }
-                            .to_variant(),
-                            3 => Vector3 {
-                                x: values[0],
-                                y: values[1],
-                                z: values[2],
-                            }
-                            .to_variant(),
-                            _ => panic!(),
-                        },
-                    );
-                }
-            }
+        for (key, value) in object.props.iter() {
+            object_data.props.insert(
+                key.as_str(),
+                match value {
+                    PropValue::Int(value) => value.to_variant(),
+                    PropValue::Float(value) => value.to_variant(),
+                    PropValue::Vector2(x, y) => Vector2 { x: *x, y: *y }.to_variant(),
+                    PropValue::Vector3(x, y, z) => Vector3 {
+                        x: *x,
+                        y: *y,
+                        z: *z,
+                    }
+                    .to_variant(),
+                    PropValue::String(value) => value.to_variant(),
+                },
+            );
+        }
+        for child in object.children.iter() {
+            object_data.children.push(convert_object(child));
         }
 
         object_data
@@ -1,57 +1,120 @@
-use godot::builtin::{Color, PackedByteArray};
-use godot::engine::global::Error;
+use godot::builtin::{PackedByteArray, Rect2, StringName, Vector2};
 use godot::engine::image::Format;
-use godot::engine::{Image, ImageTexture, SpriteFrames};
-use godot::obj::Gd;
+use godot::engine::{AtlasTexture, Image, ImageTexture, SpriteFrames};
+use godot::obj::{Gd, Share};
+use springylib::error::Error;
+use springylib::media::bmp::Bitmap;
+use springylib::media::rle::atlas::{Atlas, AtlasPacker};
 use springylib::media::rle::RleImage;
+use springylib::media::transparency::TransparencyMode;
 
 const FPS: f64 = 15.0;
+/// Largest atlas page, supported by all renderers
+const ATLAS_SIZE: u32 = 2048;
 
-pub fn load_rle_as_sprite_frames(rle: RleImage) -> Gd<SpriteFrames> {
-    let mut frames = SpriteFrames::new();
+pub fn load_rle_as_sprite_frames(
+    rle: RleImage,
+    transparency: TransparencyMode,
+) -> Result<Gd<SpriteFrames>, Error> {
+    let mut packer = AtlasPacker::new(ATLAS_SIZE, transparency);
+    packer.add("default", &rle);
 
+    let mut frames = SpriteFrames::new();
     frames.set_animation_loop("default".into(), true);
-    frames.set_animation_speed("default".into(), FPS);
-
-    for frame in rle.frames.iter() {
-        let mut image = Image::new();
-        image.set_data(
-            rle.width as i64,
-            rle.height as i64,
-            false,
-            Format::FORMAT_RGBA8,
-            PackedByteArray::from(rle.get_image_data(frame).as_slice()),
-        );
-        image.fix_alpha_edges();
-
-        let mut texture = ImageTexture::new();
-        texture.set_image(image);
-        frames.add_frame("default".into(), texture.upcast(), 1.0, 0);
-    }
-
-    frames
+    add_atlas_frames(&mut frames, packer.pack()?);
+    Ok(frames)
 }
 
-pub fn load_bmp_as_image_texture(data: Vec<u8>) -> Result<Gd<Image>, Error> {
-    let mut image = Image::new();
+/// Packs rle animations, the frames of all of them share the atlas pages
+pub fn pack_rle_animations<'a, I>(
+    animations: I,
+    transparency: TransparencyMode,
+) -> Result<Atlas, Error>
+where
+    I: IntoIterator<Item = (&'a str, &'a RleImage)>,
+{
+    let mut packer = AtlasPacker::new(ATLAS_SIZE, transparency);
+    for (name, rle) in animations {
+        packer.add(name, rle);
+    }
+    packer.pack()
+}
 
-    match image.load_bmp_from_buffer(data.as_slice().into()) {
-        Error::OK => {
-            for x in 0..image.get_width() {
-                for y in 0..image.get_height() {
-                    if image.get_pixel(x, y).is_equal_approx(Color {
-                        r: 1.0,
-                        g: 0.0,
-                        b: 1.0,
-                        a: 1.0,
-                    }) {
-                        image.set_pixel(x, y, Color::TRANSPARENT_BLACK);
-                    }
-                }
-            }
+/// Adds packed animations as [AtlasTexture] frames
+///
+/// The margin of each frame restores the full image size,
+/// so cropped frames are drawn where they belong.
+pub fn add_atlas_frames(sprite_frames: &mut SpriteFrames, atlas: Atlas) {
+    let pages: Vec<Gd<ImageTexture>> = atlas
+        .pages
+        .iter()
+        .map(|page| {
+            let mut image = Image::new();
+            image.set_data(
+                page.width as i64,
+                page.height as i64,
+                false,
+                Format::FORMAT_RGBA8,
+                PackedByteArray::from(page.data.as_slice()),
+            );
             image.fix_alpha_edges();
-            Ok(image)
+
+            let mut texture = ImageTexture::new();
+            texture.set_image(image);
+            texture
+        })
+        .collect();
+
+    for animation in atlas.animations {
+        let name = StringName::from(&animation.name);
+        if !sprite_frames.has_animation(name.clone()) {
+            sprite_frames.add_animation(name.clone());
+        }
+        sprite_frames.set_animation_speed(name.clone(), FPS);
+
+        for frame in animation.frames.iter() {
+            let mut texture = AtlasTexture::new();
+            texture.set_atlas(pages[frame.page].share().upcast());
+            texture.set_region(Rect2 {
+                position: Vector2 {
+                    x: frame.region.x as f32,
+                    y: frame.region.y as f32,
+                },
+                size: Vector2 {
+                    x: frame.region.width as f32,
+                    y: frame.region.height as f32,
+                },
+            });
+            texture.set_margin(Rect2 {
+                position: Vector2 {
+                    x: frame.offset.0 as f32,
+                    y: frame.offset.1 as f32,
+                },
+                size: Vector2 {
+                    x: animation.width.saturating_sub(frame.region.width) as f32,
+                    y: animation.height.saturating_sub(frame.region.height) as f32,
+                },
+            });
+
+            sprite_frames.add_frame(name.clone(), texture.upcast(), 1.0, 0);
         }
-        error => Err(error),
     }
 }
+
+pub fn load_bmp_as_image_texture(
+    data: Vec<u8>,
+    transparency: TransparencyMode,
+) -> Result<Gd<Image>, Error> {
+    let bitmap = Bitmap::decode_with(&data, transparency)?;
+
+    let mut image = Image::new();
+    image.set_data(
+        bitmap.width as i64,
+        bitmap.height as i64,
+        false,
+        Format::FORMAT_RGBA8,
+        PackedByteArray::from(bitmap.data.as_slice()),
+    );
+    image.fix_alpha_edges();
+    Ok(image)
+}
@@ -1,3 +1,4 @@
+use crate::sproing::image::{add_atlas_frames, pack_rle_animations};
 use godot::builtin::{GodotString, Rect2, StringName, ToVariant, Vector2};
 use godot::engine::utilities::printerr;
 use godot::engine::{
@@ -5,28 +6,55 @@ use godot::engine::{
 };
 use godot::obj::{Gd, Share};
 use godot::prelude::GodotClass;
-use springylib::media::sprites::{CropMode, RenderMode, Sprites};
+use springylib::media::rle::RleImage;
+use springylib::media::sprites::{CropMode, Sprites};
+use springylib::media::transparency::TransparencyMode;
+use std::borrow::Cow;
+use std::collections::HashMap;
 
 const FPS: f64 = 15.0;
-const SPRITE_EXTENSIONS: &[&str] = &["bmp", "rle"];
+/// rle sprites are packed into atlases, see [load_sprite_frames]
+const SPRITE_EXTENSIONS: &[&str] = &["bmp"];
 
-pub fn load_sprite_frames(sprites: Vec<Sprites>, path: GodotString) -> Gd<SpriteFrames> {
+/// Converts a sprite set, `rle_images` are the rle sprites by file name
+///
+/// The frames of all rle sprites are packed into shared atlas pages,
+/// flipped entries become mirrored copies of their source animation.
+pub fn load_sprite_frames(
+    sprites: Vec<Sprites>,
+    path: GodotString,
+    rle_images: &HashMap<String, RleImage>,
+    transparency: TransparencyMode,
+) -> Gd<SpriteFrames> {
     let dir = path
         .to_string()
         .strip_suffix("/sprites.txt")
         .unwrap()
         .to_string();
     let mut sprite_frames = SpriteFrames::new();
-    for sprite in sprites.into_iter() {
-        if let RenderMode::FlipX = sprite.render_mode {
+    let mut rle_animations = vec![];
+    for animation in Sprites::animations(&sprites) {
+        let sprite = &sprites[animation.source];
+        sprite_frames.add_animation(StringName::from(&animation.name));
+        sprite_frames.set_animation_speed(StringName::from(&animation.name), FPS);
+
+        if let Some(rle) = rle_images.get(&sprite.file_name) {
+            let rle = if animation.flip_x {
+                Cow::Owned(rle.flipped_x())
+            } else {
+                Cow::Borrowed(rle)
+            };
+            rle_animations.push((animation.name, rle));
             continue;
         }
-        sprite_frames.add_animation(StringName::from(&sprite.name));
-        sprite_frames.set_animation_speed(StringName::from(&sprite.name), FPS);
-
         match select_from_extensions(&dir, &sprite.file_name) {
-            Some((path, "rle")) => extract_rle_frames(&mut sprite_frames, &sprite, path),
-            Some((path, "bmp")) => extract_bitmap_frames(&mut sprite_frames, &sprite, path),
+            Some((path, "bmp")) => extract_bitmap_frames(
+                &mut sprite_frames,
+                &animation.name,
+                sprite,
+                path,
+                animation.flip_x,
+            ),
             Some(_) | None => {
                 printerr(
                     format!("Missing sprite '{}'", sprite.file_name).to_variant(),
@@ -34,7 +62,7 @@ pub fn load_sprite_frames(sprites: Vec<Sprites>, path: GodotString) -> Gd<Sprite
                 );
                 let texture = PlaceholderTexture2D::new();
                 sprite_frames.add_frame(
-                    StringName::from(&sprite.name),
+                    StringName::from(&animation.name),
                     texture.upcast(),
                     60.0 / FPS,
                     0,
@@ -43,29 +71,34 @@ pub fn load_sprite_frames(sprites: Vec<Sprites>, path: GodotString) -> Gd<Sprite
         }
     }
 
-    sprite_frames
-}
-
-/// Loads an RLE file as SpriteFrames and extracts
-/// its frames into `sprite_frames`
-fn extract_rle_frames(sprite_frames: &mut SpriteFrames, sprite: &Sprites, path: String) {
-    let frames: Gd<SpriteFrames> = load(path);
-    for frame_idx in 0..frames.get_frame_count("default".into()) {
-        sprite_frames.add_frame(
-            StringName::from(&sprite.name),
-            frames
-                .get_frame_texture("default".into(), frame_idx)
-                .unwrap(),
-            60.0 / FPS,
-            0,
-        );
+    let rle_animations = rle_animations
+        .iter()
+        .map(|(name, rle)| (name.as_str(), rle.as_ref()));
+    match pack_rle_animations(rle_animations, transparency) {
+        Ok(atlas) => add_atlas_frames(&mut sprite_frames, atlas),
+        Err(err) => printerr(err.to_string().to_variant(), &[]),
     }
+
+    sprite_frames
 }
 
 /// Loads a bitmap and extracts its frames into `sprite_frames`
 /// creates an atlas if there are multiple frames.
-fn extract_bitmap_frames(sprite_frames: &mut SpriteFrames, sprite: &Sprites, path: String) {
-    let texture: Gd<ImageTexture> = load(path);
+fn extract_bitmap_frames(
+    sprite_frames: &mut SpriteFrames,
+    name: &str,
+    sprite: &Sprites,
+    path: String,
+    flip_x: bool,
+) {
+    let mut texture: Gd<ImageTexture> = load(path);
+    if flip_x {
+        // frames are stacked vertically, so they stay in place
+        let mut image = texture.get_image().unwrap();
+        image.flip_x();
+        texture = ImageTexture::new();
+        texture.set_image(image);
+    }
 
     let frame_count = if let Some(CropMode::FrameCount(frame_count)) = sprite.frames {
         frame_count
@@ -92,20 +125,10 @@ fn extract_bitmap_frames(sprite_frames: &mut SpriteFrames, sprite: &Sprites, pat
                 },
             });
 
-            sprite_frames.add_frame(
-                StringName::from(&sprite.name),
-                atlas.upcast(),
-                60.0 / FPS,
-                0,
-            );
+            sprite_frames.add_frame(StringName::from(name), atlas.upcast(), 60.0 / FPS, 0);
         }
     } else {
-        sprite_frames.add_frame(
-            StringName::from(&sprite.name),
-            texture.upcast(),
-            60.0 / FPS,
-            0,
-        );
+        sprite_frames.add_frame(StringName::from(name), texture.upcast(), 60.0 / FPS, 0);
     }
 }
 
@@ -113,17 +136,11 @@ fn extract_bitmap_frames(sprite_frames: &mut SpriteFrames, sprite: &Sprites, pat
 fn select_from_extensions(dir: &str, file_name: &str) -> Option<(String, &'static str)> {
     SPRITE_EXTENSIONS
         .iter()
-        .map(|ext| {
-            (
-                format!("{}/sprites/{}.{}", dir, file_name.to_lowercase(), ext),
-                *ext,
-            )
-        })
+        .map(|ext| (format!("{}/sprites/{}.{}", dir, file_name, ext), *ext))
         .find(|(path, ext)| {
             ResourceLoader::singleton().exists(
                 path.clone().into(),
                 match *ext {
-                    "rle" => SpriteFrames::CLASS_NAME.to_string(),
                     "bmp" => ImageTexture::CLASS_NAME.to_string(),
                     _ => panic!(),
                 }
@@ -84,6 +84,12 @@ pub fn convert_ui(ui: UiTag, base_path: &str) -> Gd<Node> {
             attach_call_meta(&mut gd_button, button.on_select);
             gd_button.upcast()
         }
+        UiTag::Unknown { name, children, .. } => {
+            let mut control = Control::new_alloc();
+            control.set_name(GodotString::from(name));
+            attach_children(&mut control, children, base_path);
+            control.upcast()
+        }
     }
 }
 
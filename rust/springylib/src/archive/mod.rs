@@ -5,37 +5,108 @@ use std::io::{Read, Seek};
 use std::ops::Deref;
 
 pub mod error;
+pub mod path;
+mod probe;
+pub mod reader;
 mod v1;
 mod v2;
+pub mod verify;
+pub mod writer;
 
 use crate::archive::error::{Error, Result};
+use crate::archive::reader::EntryReader;
 
 /// Archive info
-pub struct Archive(HashMap<String, FilePointer>);
+pub struct Archive {
+    /// name in the header, such as `MHJNR-XXL`
+    name: String,
+    entries: HashMap<String, FilePointer>,
+    /// normalized path to raw paths, more than one if they only differ in case or slashes
+    index: HashMap<String, Vec<String>>,
+    /// entries that were replaced by a later entry of the same name
+    shadowed: Vec<FilePointer>,
+    table_size: usize,
+}
 
 /// Pointer to the file inside the archive
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct FilePointer {
     pub position: usize,
     pub length: usize,
     pub path: String,
 }
 
+impl FilePointer {
+    /// Opens the file as a bounded stream on top of `reader`
+    pub fn open<'a, R>(&self, reader: &'a mut R) -> std::io::Result<EntryReader<'a, R>>
+    where
+        R: Read + Seek,
+    {
+        EntryReader::new(reader, self)
+    }
+
+    /// Position and length as they are stored in the file table
+    ///
+    /// Fails for files that end up beyond 4 GiB.
+    pub(crate) fn to_u32(&self) -> Result<(u32, u32)> {
+        let too_large = |_| Error::Unsupported {
+            reason: format!("{} doesn't fit into 4 GiB", self.path),
+        };
+        Ok((
+            u32::try_from(self.position).map_err(too_large)?,
+            u32::try_from(self.length).map_err(too_large)?,
+        ))
+    }
+}
+
 impl Deref for Archive {
     type Target = HashMap<String, FilePointer>;
 
     fn deref(&self) -> &Self::Target {
-        &self.0
+        &self.entries
     }
 }
 
 impl From<HashMap<String, FilePointer>> for Archive {
     fn from(value: HashMap<String, FilePointer>) -> Self {
-        Archive(value)
+        let mut index: HashMap<String, Vec<String>> = HashMap::new();
+        for key in value.keys() {
+            index
+                .entry(path::normalize(key))
+                .or_default()
+                .push(key.clone());
+        }
+        for raw in index.values_mut() {
+            raw.sort();
+        }
+        Archive {
+            name: String::new(),
+            entries: value,
+            index,
+            shadowed: vec![],
+            table_size: 0,
+        }
+    }
+}
+
+impl FromIterator<FilePointer> for Archive {
+    fn from_iter<T: IntoIterator<Item = FilePointer>>(iter: T) -> Self {
+        let mut entries = HashMap::new();
+        let mut shadowed = vec![];
+        for pointer in iter {
+            if let Some(previous) = entries.insert(pointer.path.clone(), pointer) {
+                shadowed.push(previous);
+            }
+        }
+
+        let mut archive = Archive::from(entries);
+        archive.shadowed = shadowed;
+        archive
     }
 }
 
 /// These are all slightly divergent data layouts
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ArchiveKind {
     /// Appears in a variety of Moorhuhn Shoot 'em Up
     /// games, starting with Moorhuhn Winter.
@@ -45,7 +116,7 @@ pub enum ArchiveKind {
     /// by a final entry with the name `****`
     ///
     /// File Entries have a max path length of 0x30/0x40 with a total entry
-    /// size of 0x40.
+    /// size of 0x40/0x50.
     V1(usize),
     /// Appears in Moorhuhn Jump 'n Run games as well
     /// as Moorhuhn Kart 2, starting with Moorhuhn Kart 2.
@@ -59,21 +130,65 @@ pub enum ArchiveKind {
 }
 
 impl ArchiveKind {
-    /// Guesses the archive type based on the file type
+    /// Size of the file table, including the header
+    pub fn table_size(&self, count: usize) -> usize {
+        match self {
+            // header and terminator are entries as well
+            ArchiveKind::V1(string_size) => (count + 2) * (string_size + 0x10),
+            ArchiveKind::V2 => 0x40 + count * 0x80,
+        }
+    }
+
+    /// Longest path a file entry can hold, without the terminating null
+    ///
+    /// None if there isn't even room for the null.
+    pub fn max_path_length(&self) -> Option<usize> {
+        match self {
+            ArchiveKind::V1(string_size) => string_size.checked_sub(1),
+            ArchiveKind::V2 => Some(0x67),
+        }
+    }
+
+    /// Longest name the header can hold, without the terminating null
+    ///
+    /// None if there isn't even room for the null.
+    pub fn max_name_length(&self) -> Option<usize> {
+        match self {
+            ArchiveKind::V1(string_size) => string_size.checked_sub(1),
+            ArchiveKind::V2 => Some(0x1f),
+        }
+    }
+
+    /// Alignment of the file contents as found in the original archives
+    pub fn alignment(&self) -> usize {
+        match self {
+            ArchiveKind::V1(_) => 0x200,
+            ArchiveKind::V2 => 0x40,
+        }
+    }
+
+    /// Guesses the archive type based on the structure of the file table
+    ///
+    /// The header name is only used as a hint for which layout to try first.
     pub fn guess<R>(reader: &mut R) -> Result<ArchiveKind>
     where
         R: Read + Seek,
     {
         let name = NullString::read(reader)?.to_string();
         reader.rewind()?;
-        match name.as_str() {
-            "MHJNR-XXL" | "MHJNR-XS" | "Moorhuhn Kart 2" => Ok(ArchiveKind::V2),
-            "MH-W V1.0" | "MH3 V1.0 " | "MH 1 REMAKE" => Ok(ArchiveKind::V1(0x30)),
-            "MHP XXL" | "MHINV XXL V1.0" => Ok(ArchiveKind::V1(0x40)),
-            name => Err(Error::Unsupported {
-                reason: name.to_string(),
-            }),
+
+        let candidates = ArchiveKind::from_name(&name).into_iter().chain([
+            ArchiveKind::V2,
+            ArchiveKind::V1(0x30),
+            ArchiveKind::V1(0x40),
+        ]);
+        for kind in candidates {
+            if kind.probe(reader)? {
+                return Ok(kind);
+            }
         }
+
+        Err(Error::Unsupported { reason: name })
     }
 }
 
@@ -94,10 +209,26 @@ impl Archive {
     where
         R: Read + Seek,
     {
-        match kind {
-            ArchiveKind::V1(size) => Ok(v1::Container::read_args(reader, (size,))?.into()),
-            ArchiveKind::V2 => Ok(v2::Container::read(reader)?.into()),
-        }
+        let (count, mut archive): (usize, Archive) = match kind {
+            ArchiveKind::V1(size) => {
+                let container = v1::Container::read_args(reader, (size,))?;
+                (container.entries.len(), container.into())
+            }
+            ArchiveKind::V2 => {
+                let container = v2::Container::read(reader)?;
+                (container.files.len(), container.into())
+            }
+        };
+        archive.table_size = kind.table_size(count);
+
+        Ok(archive)
+    }
+
+    /// Name in the header, which tells the games apart
+    ///
+    /// Empty for archives that weren't read from a file.
+    pub fn name(&self) -> &str {
+        &self.name
     }
 }
 
@@ -111,6 +242,7 @@ mod tests {
         let bin = include_bytes!("v2a.dat");
         let archive = Archive::read(&mut Cursor::new(bin)).unwrap();
         assert_eq!(archive.len(), 2);
+        assert_eq!(archive.name(), "MHJNR-XXL");
         assert_eq!(
             archive["data\\config.txt"],
             FilePointer {
@@ -134,6 +266,7 @@ mod tests {
         let bin = include_bytes!("v1a.dat");
         let archive = Archive::read(&mut Cursor::new(bin)).unwrap();
         assert_eq!(archive.len(), 2);
+        assert_eq!(archive.name(), "MH 1 REMAKE");
         assert_eq!(
             archive["data\\mhx.fnt"],
             FilePointer {
@@ -157,6 +290,7 @@ mod tests {
         let bin = include_bytes!("v1b.dat");
         let archive = Archive::read(&mut Cursor::new(bin)).unwrap();
         assert_eq!(archive.len(), 2);
+        assert_eq!(archive.name(), "MHP XXL");
         assert_eq!(
             archive["data\\endbranding_xxl.txt"],
             FilePointer {
@@ -0,0 +1,293 @@
+use crate::archive::{Archive, FilePointer};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Entry of a directory listing
+#[derive(Debug, PartialEq)]
+pub enum DirEntry<'a> {
+    File(&'a FilePointer),
+    /// Normalized path of the sub directory
+    Directory(String),
+}
+
+impl Archive {
+    /// Looks up a file regardless of case and kind of slash
+    ///
+    /// If several entries only differ in case or slashes, the one
+    /// spelled exactly like `path` wins, otherwise the first by raw path.
+    /// Use [Archive::find_all] to get all of them.
+    pub fn find(&self, path: &str) -> Option<&FilePointer> {
+        self.entries
+            .get(path)
+            .or_else(|| self.find_all(path).into_iter().next())
+    }
+
+    /// All entries matching `path` regardless of case and kind of slash,
+    /// sorted by raw path
+    pub fn find_all(&self, path: &str) -> Vec<&FilePointer> {
+        self.index
+            .get(&normalize(path))
+            .into_iter()
+            .flatten()
+            .map(|raw| &self.entries[raw])
+            .collect()
+    }
+
+    /// Normalized paths that more than one entry maps to
+    pub fn collisions(&self) -> Vec<&str> {
+        let mut collisions: Vec<&str> = self
+            .index
+            .iter()
+            .filter(|(_, raw)| raw.len() > 1)
+            .map(|(normalized, _)| normalized.as_str())
+            .collect();
+        collisions.sort();
+        collisions
+    }
+
+    /// Lists the direct children of a directory,
+    /// directories first, each sorted by path
+    ///
+    /// Files that only differ in case are all listed.
+    pub fn read_dir(&self, path: &str) -> Vec<DirEntry<'_>> {
+        let dir = normalize(path);
+        let mut directories = BTreeSet::new();
+        let mut files = BTreeSet::new();
+
+        for normalized in self.index.keys() {
+            let rest = if dir.is_empty() {
+                Some(normalized.as_str())
+            } else {
+                normalized
+                    .strip_prefix(dir.as_str())
+                    .and_then(|rest| rest.strip_prefix('/'))
+            };
+            match rest.map(|rest| rest.split_once('/')) {
+                Some(Some((child, _))) => {
+                    directories.insert(join(&dir, child));
+                }
+                Some(None) => {
+                    files.insert(normalized);
+                }
+                None => {}
+            }
+        }
+
+        directories
+            .into_iter()
+            .map(DirEntry::Directory)
+            .chain(
+                files
+                    .into_iter()
+                    .flat_map(|normalized| &self.index[normalized])
+                    .map(|raw| DirEntry::File(&self.entries[raw])),
+            )
+            .collect()
+    }
+
+    /// All files matching a glob pattern, sorted by path
+    ///
+    /// See [glob_match] for the supported syntax.
+    pub fn glob(&self, pattern: &str) -> Vec<&FilePointer> {
+        let pattern = normalize(pattern);
+        let pattern: Vec<&str> = pattern.split('/').collect();
+
+        self.index
+            .iter()
+            .filter(|(normalized, _)| {
+                match_segments(&pattern, &normalized.split('/').collect::<Vec<&str>>())
+            })
+            .collect::<BTreeMap<_, _>>()
+            .into_values()
+            .flatten()
+            .map(|raw| &self.entries[raw])
+            .collect()
+    }
+}
+
+/// Normalizes a datafile path for lookups
+///
+/// The archives store paths like `data\\level01\\layers\\x.dat`, but the
+/// games themselves don't care about case or the kind of slash.
+/// Normalized paths are lowercase, use `/` and have no leading,
+/// trailing or duplicate separators.
+pub fn normalize(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|segment| !segment.is_empty() && *segment != ".")
+        .map(|segment| segment.to_lowercase())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// Joins a normalized directory and a child name
+pub fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// Returns the normalized parent directory, or an empty string for the root
+pub fn parent(path: &str) -> &str {
+    path.rsplit_once('/')
+        .map(|(parent, _)| parent)
+        .unwrap_or("")
+}
+
+/// Matches a normalized path against a glob pattern
+///
+/// * `*` matches anything inside a single path segment
+/// * `?` matches a single character
+/// * `**` matches any number of segments
+pub fn glob_match(pattern: &str, path: &str) -> bool {
+    let pattern = normalize(pattern);
+    let path = normalize(path);
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+
+    match_segments(&pattern, &path)
+}
+
+fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            match_segments(&pattern[1..], path)
+                || (!path.is_empty() && match_segments(pattern, &path[1..]))
+        }
+        (Some(segment), Some(name)) => {
+            match_segment(segment.as_bytes(), name.as_bytes())
+                && match_segments(&pattern[1..], &path[1..])
+        }
+        _ => false,
+    }
+}
+
+fn match_segment(pattern: &[u8], name: &[u8]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            match_segment(&pattern[1..], name)
+                || (!name.is_empty() && match_segment(pattern, &name[1..]))
+        }
+        (Some(b'?'), Some(_)) => match_segment(&pattern[1..], &name[1..]),
+        (Some(a), Some(b)) => a == b && match_segment(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::path::{glob_match, normalize, parent, DirEntry};
+    use crate::archive::{Archive, FilePointer};
+
+    fn archive() -> Archive {
+        [
+            "data\\config.txt",
+            "data\\Level01\\sprites.txt",
+            "data\\level01\\sprites\\hero.rle",
+            "data\\level02\\sprites\\hero.bmp",
+        ]
+        .into_iter()
+        .map(|path| FilePointer {
+            position: 0,
+            length: 0,
+            path: path.to_string(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn it_should_normalize() {
+        assert_eq!(
+            normalize("Data\\Level01\\\\layers/X.dat"),
+            "data/level01/layers/x.dat"
+        );
+        assert_eq!(normalize("/data/./fonts/"), "data/fonts");
+        assert_eq!(normalize(""), "");
+    }
+
+    #[test]
+    fn it_should_find_parent() {
+        assert_eq!(parent("data/fonts/a.bmp"), "data/fonts");
+        assert_eq!(parent("data"), "");
+    }
+
+    #[test]
+    fn it_should_glob() {
+        assert!(glob_match(
+            "data/level*/sprites/*.rle",
+            "data\\level01\\sprites\\Hero.rle"
+        ));
+        assert!(!glob_match(
+            "data/level*/sprites/*.rle",
+            "data\\level01\\sprites\\hero.bmp"
+        ));
+        assert!(!glob_match("data/*.txt", "data\\level01\\sprites.txt"));
+        assert!(glob_match("data/**/*.txt", "data\\level01\\sprites.txt"));
+        assert!(glob_match("data/**/*.txt", "data\\config.txt"));
+        assert!(glob_match("data/level0?/*", "data\\level05\\x.dat"));
+    }
+
+    #[test]
+    fn it_should_find() {
+        let archive = archive();
+        assert_eq!(
+            archive.find("DATA/level01/Sprites.txt").unwrap().path,
+            "data\\Level01\\sprites.txt"
+        );
+        assert!(archive.find("data/level01").is_none());
+    }
+
+    #[test]
+    fn it_should_read_dir() {
+        let archive = archive();
+        let entries = archive.read_dir("data");
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0], DirEntry::Directory("data/level01".to_string()));
+        assert_eq!(entries[1], DirEntry::Directory("data/level02".to_string()));
+        assert!(
+            matches!(entries[2], DirEntry::File(FilePointer { path, .. }) if path == "data\\config.txt")
+        );
+
+        assert_eq!(
+            archive.read_dir(""),
+            vec![DirEntry::Directory("data".to_string())]
+        );
+        assert!(archive.read_dir("data/level03").is_empty());
+    }
+
+    #[test]
+    fn it_should_glob_archive() {
+        let archive = archive();
+        let matches = archive.glob("data/level*/sprites/*");
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].path, "data\\level01\\sprites\\hero.rle");
+        assert_eq!(matches[1].path, "data\\level02\\sprites\\hero.bmp");
+    }
+
+    #[test]
+    fn it_should_keep_colliding_paths() {
+        let archive: Archive = [r"data\Config.txt", r"data\config.txt", "data/config.txt"]
+            .into_iter()
+            .map(|path| FilePointer {
+                position: 0,
+                length: 0,
+                path: path.to_string(),
+            })
+            .collect();
+
+        assert_eq!(archive.collisions(), vec!["data/config.txt"]);
+        assert_eq!(archive.find_all("DATA/CONFIG.TXT").len(), 3);
+        assert_eq!(
+            archive.find(r"data\config.txt").unwrap().path,
+            r"data\config.txt"
+        );
+        assert_eq!(
+            archive.find(r"data\CONFIG.txt").unwrap().path,
+            "data/config.txt"
+        );
+        assert_eq!(archive.read_dir("data").len(), 3);
+        assert_eq!(archive.glob("data/*").len(), 3);
+    }
+}
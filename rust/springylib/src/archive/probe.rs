@@ -0,0 +1,204 @@
+use crate::archive::v1::TERMINATOR;
+use crate::archive::ArchiveKind;
+use encoding_rs::WINDOWS_1252;
+use std::borrow::Cow;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+
+/// Upper bound so garbage data doesn't make us scan forever
+const MAX_ENTRIES: u64 = 0x10000;
+
+const V2_HEADER_SIZE: u64 = 0x40;
+const V2_NAME_SIZE: usize = 0x20;
+const V2_ENTRY_SIZE: u64 = 0x80;
+const V2_PATH_SIZE: usize = 0x68;
+/// Value after the count in every V2 header seen so far
+const V2_UNKNOWN: u32 = 0x100;
+
+impl ArchiveKind {
+    /// Kind that is known to be used by an archive with this header name
+    pub fn from_name(name: &str) -> Option<ArchiveKind> {
+        match name {
+            "MHJNR-XXL" | "MHJNR-XS" | "Moorhuhn Kart 2" => Some(ArchiveKind::V2),
+            "MH-W V1.0" | "MH3 V1.0 " | "MH 1 REMAKE" => Some(ArchiveKind::V1(0x30)),
+            "MHP XXL" | "MHINV XXL V1.0" => Some(ArchiveKind::V1(0x40)),
+            _ => None,
+        }
+    }
+
+    /// Checks if the file table in the stream is structurally valid for this kind
+    ///
+    /// All names have to be printable and null terminated inside their field,
+    /// the table has to fit into the stream and no entry may point into the table.
+    pub fn probe<R>(&self, reader: &mut R) -> std::io::Result<bool>
+    where
+        R: Read + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))?;
+        reader.rewind()?;
+        let result = match self {
+            ArchiveKind::V1(string_size) => probe_v1(reader, *string_size, length),
+            ArchiveKind::V2 => probe_v2(reader, length),
+        };
+        reader.rewind()?;
+
+        match result {
+            Ok(valid) => Ok(valid),
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+fn probe_v1<R>(reader: &mut R, string_size: usize, length: u64) -> std::io::Result<bool>
+where
+    R: Read,
+{
+    let entry_size = string_size as u64 + 0x10;
+    let mut entry = vec![0u8; entry_size as usize];
+
+    reader.read_exact(&mut entry)?;
+    if read_name(&entry[..string_size]).is_none() {
+        return Ok(false);
+    }
+
+    let mut pointers = vec![];
+    loop {
+        if (pointers.len() as u64 + 2) * entry_size > length || pointers.len() as u64 > MAX_ENTRIES
+        {
+            return Ok(false);
+        }
+        reader.read_exact(&mut entry)?;
+        match read_name(&entry[..string_size]) {
+            Some(name) if name == TERMINATOR => break,
+            Some(_) => pointers.push(read_pointer(&entry[string_size..])),
+            None => return Ok(false),
+        }
+    }
+
+    let table_end = (pointers.len() as u64 + 2) * entry_size;
+    Ok(pointers
+        .into_iter()
+        .all(|pointer| is_plausible(pointer, table_end)))
+}
+
+fn probe_v2<R>(reader: &mut R, length: u64) -> std::io::Result<bool>
+where
+    R: Read,
+{
+    let mut header = [0u8; V2_HEADER_SIZE as usize];
+    reader.read_exact(&mut header)?;
+    if read_name(&header[..V2_NAME_SIZE]).is_none() {
+        return Ok(false);
+    }
+
+    let (count, unknown) = read_pointer(&header[V2_NAME_SIZE..]);
+    let table_end = V2_HEADER_SIZE + count * V2_ENTRY_SIZE;
+    if count > MAX_ENTRIES || table_end > length {
+        return Ok(false);
+    }
+    // a V1 header has zeros here, so an empty table needs more to go on
+    if count == 0 {
+        return Ok(unknown == V2_UNKNOWN as u64);
+    }
+
+    let mut entry = [0u8; V2_ENTRY_SIZE as usize];
+    for _ in 0..count {
+        reader.read_exact(&mut entry)?;
+        if read_name(&entry[..V2_PATH_SIZE]).is_none()
+            || !is_plausible(read_pointer(&entry[V2_PATH_SIZE..]), table_end)
+        {
+            return Ok(false);
+        }
+    }
+
+    Ok(true)
+}
+
+/// Reads a null terminated name, which has to be non-empty and printable
+///
+/// Names are Windows-1252 like the rest of the game's text.
+fn read_name(field: &[u8]) -> Option<Cow<'_, str>> {
+    let end = field.iter().position(|c| *c == 0)?;
+    Some(WINDOWS_1252.decode_without_bom_handling(&field[..end]).0)
+        .filter(|name| !name.is_empty() && name.chars().all(|c| !c.is_control()))
+}
+
+fn read_pointer(field: &[u8]) -> (u64, u64) {
+    (
+        u32::from_le_bytes(field[0..4].try_into().unwrap()) as u64,
+        u32::from_le_bytes(field[4..8].try_into().unwrap()) as u64,
+    )
+}
+
+/// Files are always stored after the table and must be addressable
+fn is_plausible((position, length): (u64, u64), table_end: u64) -> bool {
+    position >= table_end && position + length <= u32::MAX as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::writer::ArchiveWriter;
+    use crate::archive::{Archive, ArchiveKind};
+    use std::io::{Cursor, Seek};
+
+    /// Replaces the header name with one that isn't known
+    fn rename(bin: &[u8], name: &[u8]) -> Vec<u8> {
+        let mut bin = bin.to_vec();
+        bin[..0x20].fill(0);
+        bin[..name.len()].copy_from_slice(name);
+        bin
+    }
+
+    #[test]
+    fn it_should_detect_unknown_names() {
+        for (bin, kind) in [
+            (&include_bytes!("v1a.dat")[..], ArchiveKind::V1(0x30)),
+            (&include_bytes!("v1b.dat")[..], ArchiveKind::V1(0x40)),
+            (&include_bytes!("v2a.dat")[..], ArchiveKind::V2),
+        ] {
+            assert_eq!(ArchiveKind::from_name("MHX UNKNOWN"), None);
+            // the second one is Windows-1252 like regional releases
+            for name in [&b"MHX UNKNOWN\0"[..], b"MHX K\xe4fer\0"] {
+                let bin = rename(bin, name);
+                assert_eq!(ArchiveKind::guess(&mut Cursor::new(&bin)).unwrap(), kind);
+                assert_eq!(Archive::read(&mut Cursor::new(&bin)).unwrap().len(), 2);
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_detect_written_archives() {
+        for kind in [
+            ArchiveKind::V1(0x30),
+            ArchiveKind::V1(0x40),
+            ArchiveKind::V2,
+        ] {
+            for files in [&[("data\\a.txt", b"hello")][..], &[]] {
+                let mut writer = ArchiveWriter::new(kind, "Moorhuhn X");
+                for (path, data) in files {
+                    writer.add_file(path, data.to_vec()).unwrap();
+                }
+                let mut out = Cursor::new(Vec::new());
+                writer.write(&mut out).unwrap();
+                out.rewind().unwrap();
+
+                assert_eq!(ArchiveKind::guess(&mut out).unwrap(), kind);
+                assert_eq!(Archive::read(&mut out).unwrap().len(), files.len());
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_reject_garbage() {
+        assert!(ArchiveKind::guess(&mut Cursor::new(b"MHX\0garbage")).is_err());
+        assert!(ArchiveKind::guess(&mut Cursor::new(vec![0u8; 0x200])).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_entries_inside_table() {
+        let mut bin = include_bytes!("v2a.dat").to_vec();
+        // position of the first entry
+        bin[0xa8..0xac].copy_from_slice(&0x20u32.to_le_bytes());
+        assert!(!ArchiveKind::V2.probe(&mut Cursor::new(&bin)).unwrap());
+    }
+}
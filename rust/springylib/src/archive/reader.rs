@@ -0,0 +1,212 @@
+use crate::archive::error::Result;
+use crate::archive::{Archive, FilePointer};
+use crate::error::Error;
+use crate::DatafileFile;
+use std::io::{ErrorKind, Read, Seek, SeekFrom};
+use std::ops::Deref;
+
+/// Archive that keeps its underlying stream open
+///
+/// Entries are handed out as bounded sub-streams, so the
+/// file only has to be opened once.
+pub struct ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    archive: Archive,
+    reader: R,
+}
+
+impl<R> ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    /// Reads the file table and takes ownership of the stream
+    pub fn new(mut reader: R) -> Result<ArchiveReader<R>> {
+        let archive = Archive::read(&mut reader)?;
+        Ok(ArchiveReader { archive, reader })
+    }
+
+    /// Opens the entry at `path` as a bounded stream
+    ///
+    /// Paths are looked up regardless of case and kind of slash.
+    pub fn open(&mut self, path: &str) -> std::io::Result<EntryReader<'_, R>> {
+        let pointer = self
+            .archive
+            .find(path)
+            .ok_or(std::io::Error::new(ErrorKind::NotFound, path))?;
+        EntryReader::new(&mut self.reader, pointer)
+    }
+
+    /// Loads and decodes the entry at `path`
+    pub fn load(&mut self, path: &str) -> std::result::Result<DatafileFile, Error> {
+        let pointer = self
+            .archive
+            .find(path)
+            .ok_or_else(|| Error::InvalidPath(path.to_string()))?;
+        pointer.load_from(&mut self.reader)
+    }
+
+    pub fn archive(&self) -> &Archive {
+        &self.archive
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R> Deref for ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    type Target = Archive;
+
+    fn deref(&self) -> &Self::Target {
+        &self.archive
+    }
+}
+
+/// Bounded view of a single file inside the archive
+///
+/// Unlike binrw's `TakeSeek`, positions are relative to the
+/// start of the entry, so decoders can seek freely inside it.
+pub struct EntryReader<'a, R>
+where
+    R: Read + Seek,
+{
+    reader: &'a mut R,
+    start: u64,
+    length: u64,
+    position: u64,
+}
+
+impl<'a, R> EntryReader<'a, R>
+where
+    R: Read + Seek,
+{
+    pub fn new(reader: &'a mut R, pointer: &FilePointer) -> std::io::Result<EntryReader<'a, R>> {
+        let start = pointer.position as u64;
+        reader.seek(SeekFrom::Start(start))?;
+        Ok(EntryReader {
+            reader,
+            start,
+            length: pointer.length as u64,
+            position: 0,
+        })
+    }
+
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+impl<'a, R> Read for EntryReader<'a, R>
+where
+    R: Read + Seek,
+{
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let remaining = self.length.saturating_sub(self.position);
+        if remaining == 0 {
+            return Ok(0);
+        }
+
+        let max = (buf.len() as u64).min(remaining) as usize;
+        let n = self.reader.read(&mut buf[..max])?;
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+
+impl<'a, R> Seek for EntryReader<'a, R>
+where
+    R: Read + Seek,
+{
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length.checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        }
+        .ok_or(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "invalid seek to a negative or overflowing position",
+        ))?;
+
+        self.reader.seek(SeekFrom::Start(self.start + position))?;
+        self.position = position;
+        Ok(position)
+    }
+
+    fn stream_position(&mut self) -> std::io::Result<u64> {
+        Ok(self.position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::reader::ArchiveReader;
+    use crate::archive::writer::ArchiveWriter;
+    use crate::archive::ArchiveKind;
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    fn archive() -> ArchiveReader<Cursor<Vec<u8>>> {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V2, "MHJNR-XXL");
+        writer.add_file("data\\a.txt", b"hello".to_vec()).unwrap();
+        writer.add_file("data\\b.txt", b"world!".to_vec()).unwrap();
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out).unwrap();
+        out.rewind().unwrap();
+
+        ArchiveReader::new(out).unwrap()
+    }
+
+    #[test]
+    fn it_should_read_entries() {
+        let mut archive = archive();
+        assert_eq!(archive.len(), 2);
+
+        let mut data = String::new();
+        archive
+            .open("data\\b.txt")
+            .unwrap()
+            .read_to_string(&mut data)
+            .unwrap();
+        assert_eq!(data, "world!");
+
+        let mut data = String::new();
+        archive
+            .open("DATA/A.txt")
+            .unwrap()
+            .read_to_string(&mut data)
+            .unwrap();
+        assert_eq!(data, "hello");
+    }
+
+    #[test]
+    fn it_should_seek_relative_to_entry() {
+        let mut archive = archive();
+        let mut entry = archive.open("data\\b.txt").unwrap();
+        let mut data = [0u8; 3];
+
+        entry.seek(SeekFrom::End(-3)).unwrap();
+        entry.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"ld!");
+
+        assert_eq!(entry.seek(SeekFrom::Start(1)).unwrap(), 1);
+        entry.read_exact(&mut data).unwrap();
+        assert_eq!(&data, b"orl");
+
+        assert!(entry.seek(SeekFrom::Current(-5)).is_err());
+    }
+
+    #[test]
+    fn it_should_fail_on_missing_entries() {
+        let mut archive = archive();
+        assert!(archive.open("data\\c.txt").is_err());
+    }
+}
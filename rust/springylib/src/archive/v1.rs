@@ -1,44 +1,58 @@
+use crate::archive::error::Error;
 use crate::archive::{Archive, FilePointer};
-use binrw::{binread, parser, until_exclusive, BinResult, NullString};
+use binrw::{binrw, parser, until_exclusive, writer, BinResult, BinWrite, NullString};
 
-#[binread]
-#[br(little, import(string_size: usize))]
+pub const TERMINATOR: &str = "****";
+
+#[binrw]
+#[brw(little, import(string_size: usize))]
 #[derive(Debug)]
 pub struct Container {
-    #[br(temp, args(string_size))]
+    #[brw(args(string_size))]
     pub header: FileEntry,
     #[br(parse_with = until_end, args_raw((string_size,)))]
+    #[bw(write_with = write_until_end, args(string_size, header.pointer[0]))]
     pub entries: Vec<FileEntry>,
 }
 
 #[parser(reader, endian)]
 fn until_end(string_size: usize) -> BinResult<Vec<FileEntry>> {
-    until_exclusive(|entry: &FileEntry| entry.name.to_string().as_str() == "****")(
+    until_exclusive(|entry: &FileEntry| entry.name.to_string().as_str() == TERMINATOR)(
         reader,
         endian,
         (string_size,),
     )
 }
 
+#[writer(writer, endian)]
+fn write_until_end(
+    entries: &Vec<FileEntry>,
+    string_size: usize,
+    data_offset: u32,
+) -> BinResult<()> {
+    entries.write_options(writer, endian, (string_size,))?;
+    FileEntry {
+        name: TERMINATOR.into(),
+        pointer: [data_offset, 0],
+    }
+    .write_options(writer, endian, (string_size,))
+}
+
 impl From<Container> for Archive {
     fn from(value: Container) -> Self {
-        Archive(
-            value
-                .entries
-                .into_iter()
-                .map(|entry| (entry.name.to_string(), entry.into()))
-                .collect(),
-        )
+        let mut archive: Archive = value.entries.into_iter().map(FilePointer::from).collect();
+        archive.name = value.header.name.to_string();
+        archive
     }
 }
 
-#[binread]
-#[br(little, import(string_size: usize))]
+#[binrw]
+#[brw(little, import(string_size: usize))]
 #[derive(Debug)]
 pub struct FileEntry {
-    #[br(pad_size_to = string_size)]
+    #[brw(pad_size_to = string_size)]
     pub name: NullString,
-    #[br(pad_size_to = 0x10)]
+    #[brw(pad_size_to = 0x10)]
     pub pointer: [u32; 2],
 }
 
@@ -51,3 +65,15 @@ impl From<FileEntry> for FilePointer {
         }
     }
 }
+
+impl TryFrom<&FilePointer> for FileEntry {
+    type Error = Error;
+
+    fn try_from(value: &FilePointer) -> Result<Self, Self::Error> {
+        let (position, length) = value.to_u32()?;
+        Ok(FileEntry {
+            name: value.path.as_str().into(),
+            pointer: [position, length],
+        })
+    }
+}
@@ -1,16 +1,18 @@
+use crate::archive::error::Error;
 use crate::archive::{Archive, FilePointer};
-use binrw::prelude::binread;
+use binrw::prelude::binrw;
 use binrw::NullString;
 
-#[binread]
-#[br(little)]
+#[binrw]
+#[brw(little)]
 #[derive(Debug)]
 pub struct Container {
-    #[br(align_after = 0x20)]
+    #[brw(align_after = 0x20)]
     pub name: NullString,
     #[br(temp)]
+    #[bw(calc = files.len() as u32)]
     pub count: u32,
-    #[br(align_after = 0x20)]
+    #[brw(align_after = 0x20)]
     pub unk1: u32,
     #[br(count = count)]
     pub files: Vec<FileEntry>,
@@ -18,23 +20,19 @@ pub struct Container {
 
 impl From<Container> for Archive {
     fn from(value: Container) -> Self {
-        Archive(
-            value
-                .files
-                .into_iter()
-                .map(|it| (it.name.to_string(), it.into()))
-                .collect(),
-        )
+        let mut archive: Archive = value.files.into_iter().map(FilePointer::from).collect();
+        archive.name = value.name.to_string();
+        archive
     }
 }
 
 #[derive(Debug)]
-#[binread]
+#[binrw]
 pub struct FileEntry {
-    #[br(pad_size_to = 0x68)]
+    #[brw(pad_size_to = 0x68)]
     pub name: NullString,
     pub pos: u32,
-    #[br(pad_after = 0x10)]
+    #[brw(pad_after = 0x10)]
     pub len: u32,
 }
 
@@ -47,3 +45,16 @@ impl From<FileEntry> for FilePointer {
         }
     }
 }
+
+impl TryFrom<&FilePointer> for FileEntry {
+    type Error = Error;
+
+    fn try_from(value: &FilePointer) -> Result<Self, Self::Error> {
+        let (pos, len) = value.to_u32()?;
+        Ok(FileEntry {
+            name: value.path.as_str().into(),
+            pos,
+            len,
+        })
+    }
+}
@@ -0,0 +1,180 @@
+use crate::archive::path::normalize;
+use crate::archive::{Archive, FilePointer};
+use itertools::Itertools;
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::Range;
+
+/// Result of an integrity check
+#[derive(Debug, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Entries that don't fit into the file or start inside the file table
+    pub out_of_bounds: Vec<String>,
+    /// Pairs of entries that share at least one byte
+    pub overlaps: Vec<(String, String)>,
+    /// Names that appear more than once, ignoring case and kind of slash
+    pub duplicates: Vec<String>,
+    /// Ranges that are not referenced by any entry, but contain data
+    pub unreferenced: Vec<Range<usize>>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.out_of_bounds.is_empty()
+            && self.overlaps.is_empty()
+            && self.duplicates.is_empty()
+            && self.unreferenced.is_empty()
+    }
+}
+
+impl Archive {
+    /// Checks the file table against the contents of `reader`
+    pub fn verify<R>(&self, reader: &mut R) -> std::io::Result<VerifyReport>
+    where
+        R: Read + Seek,
+    {
+        let length = reader.seek(SeekFrom::End(0))? as usize;
+        let pointers: Vec<&FilePointer> = self
+            .entries
+            .values()
+            .chain(self.shadowed.iter())
+            .sorted_by_key(|pointer| (pointer.position, pointer.length, &pointer.path))
+            .collect();
+
+        let mut report = VerifyReport {
+            duplicates: pointers
+                .iter()
+                .map(|pointer| normalize(&pointer.path))
+                .duplicates()
+                .sorted()
+                .collect(),
+            ..Default::default()
+        };
+
+        let mut covered = self.table_size;
+        let mut last: Option<&FilePointer> = None;
+        for pointer in pointers.iter().copied() {
+            let end = pointer.position + pointer.length;
+            if pointer.position < self.table_size || end > length {
+                report.out_of_bounds.push(pointer.path.clone());
+            }
+            if pointer.length == 0 {
+                continue;
+            }
+
+            if let Some(last) = last.filter(|_| pointer.position < covered) {
+                report
+                    .overlaps
+                    .push((last.path.clone(), pointer.path.clone()));
+            } else if pointer.position > covered {
+                let gap = covered..pointer.position.min(length);
+                if contains_data(reader, gap.clone())? {
+                    report.unreferenced.push(gap);
+                }
+            }
+
+            if end > covered {
+                covered = end;
+                last = Some(pointer);
+            }
+        }
+
+        if covered < length && contains_data(reader, covered..length)? {
+            report.unreferenced.push(covered..length);
+        }
+
+        Ok(report)
+    }
+}
+
+/// Checks if there is anything but padding in the range
+fn contains_data<R>(reader: &mut R, range: Range<usize>) -> std::io::Result<bool>
+where
+    R: Read + Seek,
+{
+    if range.is_empty() {
+        return Ok(false);
+    }
+
+    reader.seek(SeekFrom::Start(range.start as u64))?;
+    let mut remaining = range.len();
+    let mut buffer = [0u8; 0x1000];
+    while remaining > 0 {
+        let chunk = remaining.min(buffer.len());
+        reader.read_exact(&mut buffer[..chunk])?;
+        if buffer[..chunk].iter().any(|byte| *byte != 0) {
+            return Ok(true);
+        }
+        remaining -= chunk;
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::writer::ArchiveWriter;
+    use crate::archive::{Archive, ArchiveKind, FilePointer};
+    use std::io::Cursor;
+
+    fn pointer(path: &str, position: usize, length: usize) -> FilePointer {
+        FilePointer {
+            position,
+            length,
+            path: path.to_string(),
+        }
+    }
+
+    #[test]
+    fn it_should_accept_written_archives() {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V1(0x30), "MH 1 REMAKE");
+        writer.add_file("data\\a.txt", b"hello".to_vec()).unwrap();
+        writer.add_file("data\\b.txt", b"world!".to_vec()).unwrap();
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out).unwrap();
+
+        let archive = Archive::read(&mut Cursor::new(out.get_ref())).unwrap();
+        let report = archive.verify(&mut out).unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+    }
+
+    #[test]
+    fn it_should_report_problems() {
+        let mut data = vec![0u8; 0x100];
+        data[0x80] = 1;
+        data[0x10..0x28].fill(1);
+
+        let archive: Archive = [
+            pointer("data\\a.txt", 0x10, 0x10),
+            pointer("data\\b.txt", 0x18, 0x10),
+            pointer("data\\c.txt", 0xf0, 0x20),
+            pointer("data\\d.txt", 0x30, 0x10),
+            pointer("DATA/D.TXT", 0x40, 0x10),
+        ]
+        .into_iter()
+        .collect();
+
+        let report = archive.verify(&mut Cursor::new(data)).unwrap();
+        assert_eq!(report.out_of_bounds, vec!["data\\c.txt".to_string()]);
+        assert_eq!(
+            report.overlaps,
+            vec![("data\\a.txt".to_string(), "data\\b.txt".to_string())]
+        );
+        assert_eq!(report.duplicates, vec!["data/d.txt".to_string()]);
+        assert_eq!(report.unreferenced, vec![0x50..0xf0]);
+        assert!(!report.is_ok());
+    }
+
+    #[test]
+    fn it_should_report_shadowed_duplicates() {
+        let archive: Archive = [
+            pointer("data\\a.txt", 0x0, 0x10),
+            pointer("data\\a.txt", 0x10, 0x10),
+        ]
+        .into_iter()
+        .collect();
+
+        let report = archive.verify(&mut Cursor::new(vec![0u8; 0x20])).unwrap();
+        assert_eq!(archive.len(), 1);
+        assert_eq!(report.duplicates, vec!["data/a.txt".to_string()]);
+    }
+}
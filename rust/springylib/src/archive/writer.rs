@@ -0,0 +1,281 @@
+use crate::archive::error::{Error, Result};
+use crate::archive::{v1, v2, Archive, ArchiveKind, FilePointer};
+use binrw::BinWrite;
+use std::io::{Seek, Write};
+
+/// Builds a datafile from a set of paths and their contents
+///
+/// Files are laid out in the order they were added, starting at
+/// `data_offset` (or right after the file table if unset), with each
+/// file aligned to `alignment`.
+pub struct ArchiveWriter {
+    pub kind: ArchiveKind,
+    pub name: String,
+    pub data_offset: Option<usize>,
+    pub alignment: usize,
+    pub files: Vec<(String, Vec<u8>)>,
+}
+
+impl ArchiveWriter {
+    pub fn new(kind: ArchiveKind, name: &str) -> ArchiveWriter {
+        ArchiveWriter {
+            kind,
+            name: name.to_string(),
+            data_offset: None,
+            alignment: kind.alignment(),
+            files: vec![],
+        }
+    }
+
+    /// Adds a file, paths use backslashes like `data\\config.txt`
+    ///
+    /// Fails if the path doesn't fit into a file entry.
+    pub fn add_file(&mut self, path: &str, data: Vec<u8>) -> Result<()> {
+        check_length("path", path, self.kind.max_path_length())?;
+        self.files.push((path.to_string(), data));
+        Ok(())
+    }
+
+    /// Size of the file table, including the header
+    pub fn table_size(&self) -> usize {
+        self.kind.table_size(self.files.len())
+    }
+
+    /// Computes where each file will end up inside the archive
+    pub fn layout(&self) -> Vec<FilePointer> {
+        let mut position = self
+            .data_offset
+            .unwrap_or_else(|| align(self.table_size(), self.alignment));
+
+        self.files
+            .iter()
+            .map(|(path, data)| {
+                let pointer = FilePointer {
+                    position,
+                    length: data.len(),
+                    path: path.clone(),
+                };
+                position = align(position + data.len(), self.alignment);
+                pointer
+            })
+            .collect()
+    }
+
+    /// Writes the archive and returns the resulting file table
+    pub fn write<W>(&self, writer: &mut W) -> Result<Archive>
+    where
+        W: Write + Seek,
+    {
+        check_length("name", &self.name, self.kind.max_name_length())?;
+        for (path, _) in &self.files {
+            check_length("path", path, self.kind.max_path_length())?;
+        }
+
+        let layout = self.layout();
+        let data_offset = self
+            .data_offset
+            .unwrap_or_else(|| align(self.table_size(), self.alignment));
+
+        match self.kind {
+            ArchiveKind::V1(string_size) => v1::Container {
+                header: v1::FileEntry {
+                    name: self.name.as_str().into(),
+                    pointer: [to_u32("data offset", data_offset)?, 1],
+                },
+                entries: layout
+                    .iter()
+                    .map(v1::FileEntry::try_from)
+                    .collect::<Result<_>>()?,
+            }
+            .write_args(writer, (string_size,))?,
+            ArchiveKind::V2 => v2::Container {
+                name: self.name.as_str().into(),
+                unk1: 0x100,
+                files: layout
+                    .iter()
+                    .map(v2::FileEntry::try_from)
+                    .collect::<Result<_>>()?,
+            }
+            .write(writer)?,
+        }
+
+        let mut position = self.table_size();
+        for (pointer, (_, data)) in layout.iter().zip(self.files.iter()) {
+            if pointer.position < position {
+                return Err(Error::Unsupported {
+                    reason: format!("{} overlaps the file table", pointer.path),
+                });
+            }
+            writer.write_all(&vec![0u8; pointer.position - position])?;
+            writer.write_all(data)?;
+            position = pointer.position + data.len();
+        }
+
+        let mut archive: Archive = layout.into_iter().collect();
+        archive.table_size = self.table_size();
+        Ok(archive)
+    }
+}
+
+/// The entries have fixed size fields, longer strings would shift the whole table
+fn check_length(what: &str, value: &str, max: Option<usize>) -> Result<()> {
+    let Some(max) = max else {
+        return Err(Error::Unsupported {
+            reason: format!("no room for the {} {}", what, value),
+        });
+    };
+    if value.len() > max {
+        return Err(Error::Unsupported {
+            reason: format!(
+                "{} {} is {} bytes long, at most {} fit",
+                what,
+                value,
+                value.len(),
+                max
+            ),
+        });
+    }
+    Ok(())
+}
+
+fn to_u32(what: &str, value: usize) -> Result<u32> {
+    u32::try_from(value).map_err(|_| Error::Unsupported {
+        reason: format!("{} {:#x} doesn't fit into 4 GiB", what, value),
+    })
+}
+
+fn align(position: usize, alignment: usize) -> usize {
+    if alignment <= 1 {
+        position
+    } else {
+        position.div_ceil(alignment) * alignment
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::writer::ArchiveWriter;
+    use crate::archive::{Archive, ArchiveKind};
+    use std::io::Cursor;
+
+    fn round_trip(
+        bin: &[u8],
+        kind: ArchiveKind,
+        name: &str,
+        offset: usize,
+        files: &[(&str, usize)],
+    ) {
+        let mut writer = ArchiveWriter::new(kind, name);
+        writer.data_offset = Some(offset);
+        for (path, length) in files {
+            writer.add_file(path, vec![0u8; *length]).unwrap();
+        }
+
+        let mut out = Cursor::new(Vec::new());
+        let written = writer.write(&mut out).unwrap();
+        let out = out.into_inner();
+        assert_eq!(&out[..bin.len()], bin);
+
+        let archive = Archive::read(&mut Cursor::new(out.as_slice())).unwrap();
+        let expected = Archive::read(&mut Cursor::new(bin)).unwrap();
+        assert_eq!(*archive, *expected);
+        assert_eq!(*written, *expected);
+    }
+
+    #[test]
+    fn it_should_write_v1a() {
+        round_trip(
+            include_bytes!("v1a.dat"),
+            ArchiveKind::V1(0x30),
+            "MH 1 REMAKE",
+            0x1200,
+            &[("data\\mhx.fnt", 0x8d9), ("data\\text.txt", 0x427e)],
+        );
+    }
+
+    #[test]
+    fn it_should_write_v1b() {
+        round_trip(
+            include_bytes!("v1b.dat"),
+            ArchiveKind::V1(0x40),
+            "MHP XXL",
+            0x7000,
+            &[
+                ("data\\endbranding_xxl.txt", 0x40),
+                ("data\\settings_xxl.txt", 0x872),
+            ],
+        );
+    }
+
+    #[test]
+    fn it_should_write_v2a() {
+        round_trip(
+            include_bytes!("v2a.dat"),
+            ArchiveKind::V2,
+            "MHJNR-XXL",
+            0x57b40,
+            &[
+                ("data\\config.txt", 0xf4),
+                ("data\\fonts\\dangerfont.bmp", 0x7dfd8),
+            ],
+        );
+    }
+
+    #[test]
+    fn it_should_place_data_after_table() {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V1(0x40), "MHP XXL");
+        writer.add_file("data\\a.txt", b"hello".to_vec()).unwrap();
+        assert_eq!(writer.table_size(), 0xf0);
+
+        let mut out = Cursor::new(Vec::new());
+        let archive = writer.write(&mut out).unwrap();
+        assert_eq!(archive["data\\a.txt"].position, 0x200);
+    }
+
+    #[test]
+    fn it_should_write_contents() {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V2, "MHJNR-XXL");
+        writer.add_file("data\\a.txt", b"hello".to_vec()).unwrap();
+        writer.add_file("data\\b.txt", b"world!".to_vec()).unwrap();
+
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out).unwrap();
+        let out = out.into_inner();
+
+        let archive = Archive::read(&mut Cursor::new(out.as_slice())).unwrap();
+        let a = &archive["data\\a.txt"];
+        let b = &archive["data\\b.txt"];
+        assert_eq!(a.position, 0x140);
+        assert_eq!(b.position, 0x180);
+        assert_eq!(&out[a.position..a.position + a.length], b"hello");
+        assert_eq!(&out[b.position..b.position + b.length], b"world!");
+    }
+
+    #[test]
+    fn it_should_reject_long_strings() {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V1(0x30), "MH 1 REMAKE");
+        assert!(writer.add_file(&"a".repeat(0x2f), vec![]).is_ok());
+        assert!(writer.add_file(&"a".repeat(0x30), vec![]).is_err());
+
+        let mut writer = ArchiveWriter::new(ArchiveKind::V2, &"a".repeat(0x20));
+        writer.add_file(&"a".repeat(0x67), vec![]).unwrap();
+        assert!(writer.add_file(&"a".repeat(0x68), vec![]).is_err());
+        assert!(writer.write(&mut Cursor::new(Vec::new())).is_err());
+
+        writer.name = "MHJNR-XXL".to_string();
+        writer.files.push(("a".repeat(0x68), vec![]));
+        assert!(writer.write(&mut Cursor::new(Vec::new())).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_large_offsets() {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V2, "MHJNR-XXL");
+        writer.add_file("data\\a.txt", vec![]).unwrap();
+        writer.data_offset = Some(u32::MAX as usize + 1);
+        assert!(writer.write(&mut Cursor::new(Vec::new())).is_err());
+
+        let mut writer = ArchiveWriter::new(ArchiveKind::V1(0), "");
+        assert!(writer.add_file("a", vec![]).is_err());
+        assert!(writer.write(&mut Cursor::new(Vec::new())).is_err());
+    }
+}
@@ -53,6 +53,12 @@ impl From<serde_xml_rs::Error> for Error {
     }
 }
 
+impl From<xml::reader::Error> for Error {
+    fn from(value: xml::reader::Error) -> Self {
+        Error::Custom(Box::new(value))
+    }
+}
+
 impl From<std::string::FromUtf8Error> for Error {
     fn from(value: std::string::FromUtf8Error) -> Self {
         Error::Custom(Box::new(value))
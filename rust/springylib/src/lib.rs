@@ -3,19 +3,18 @@ use crate::error::Error;
 use crate::media::level::LevelLayer;
 use crate::media::rle::RleImage;
 use crate::media::sprites::Sprites;
+use crate::media::tile_collision::TileCollisionMap;
+use crate::media::translations::Translations;
 use crate::media::txt::{decrypt_exposed_txt, decrypt_txt};
 use crate::media::ui::UiTag;
 use binrw::prelude::BinRead;
-use encoding_rs::WINDOWS_1252;
-use itertools::Itertools;
-use std::collections::HashMap;
-use std::ffi::OsStr;
-use std::io::{Cursor, Read, Seek, SeekFrom};
-use std::path::Path;
+use std::io::{Read, Seek};
 
 pub mod archive;
 pub mod error;
 pub mod media;
+pub mod tiled;
+pub mod vfs;
 
 pub enum DatafileFile {
     Txt(String),
@@ -24,9 +23,9 @@ pub enum DatafileFile {
     RleSprite(Box<RleImage>),
     Bitmap(Vec<u8>),
     Vorbis(Vec<u8>),
-    TileCollision(String),
+    TileCollision(TileCollisionMap),
     Ui(UiTag),
-    Translations(HashMap<String, Vec<String>>),
+    Translations(Translations),
 }
 
 impl FilePointer {
@@ -34,59 +33,53 @@ impl FilePointer {
     where
         R: Read + Seek,
     {
-        reader.seek(SeekFrom::Start(self.position as u64))?;
-        let mut data = vec![0u8; self.length as usize];
-        reader.read_exact(&mut data)?;
-        let path = Path::new(&self.path);
+        load_file(&self.path, &mut self.open(reader)?)
+    }
+}
+
+/// Decodes a file based on its name
+///
+/// Works with both backslash and forward slash separated paths.
+pub fn load_file<R>(path: &str, reader: &mut R) -> Result<DatafileFile, Error>
+where
+    R: Read + Seek,
+{
+    let file_name = path.rsplit(['/', '\\']).next().unwrap_or(path);
+    let (stem, extension) = file_name
+        .rsplit_once('.')
+        .ok_or(Error::InvalidExtension(None))?;
+
+    match extension {
+        "dat" => Ok(DatafileFile::Level(LevelLayer::read(reader)?)),
+        "rle" => Ok(DatafileFile::RleSprite(Box::new(RleImage::read(reader)?))),
+        _ => {
+            let mut data = vec![];
+            reader.read_to_end(&mut data)?;
+            decode(stem, extension, data)
+        }
+    }
+}
 
-        match path
-            .extension()
-            .and_then(OsStr::to_str)
-            .ok_or(Error::InvalidExtension(None))?
-        {
-            "dat" => Ok(DatafileFile::Level(LevelLayer::read(&mut Cursor::new(
-                data,
-            ))?)),
-            "rle" => Ok(DatafileFile::RleSprite(Box::new(RleImage::read(
-                &mut Cursor::new(data),
-            )?))),
-            "bmp" => Ok(DatafileFile::Bitmap(data)),
-            "ogg" => Ok(DatafileFile::Vorbis(data)),
-            "xml" => Ok(DatafileFile::Ui(
-                serde_xml_rs::from_str::<UiTag>(String::from_utf8(data)?.as_str())?.post_process(),
-            )),
-            "txt" => {
-                let stem = path
-                    .file_stem()
-                    .and_then(OsStr::to_str)
-                    .ok_or_else(|| Error::InvalidPath(path.to_string_lossy().to_string()))?;
-                let decr = decrypt_txt(data.into_iter())?;
-                if stem.starts_with("tile_collision") {
-                    Ok(DatafileFile::TileCollision(decr))
-                } else if stem == "sprites" {
-                    Ok(DatafileFile::Sprites(Sprites::parse(decr.as_str())?))
-                } else if stem.starts_with("profile") || stem.starts_with("highscores") {
-                    Ok(DatafileFile::Txt(decrypt_exposed_txt(decr)?))
-                } else {
-                    Ok(DatafileFile::Txt(decr))
-                }
+fn decode(stem: &str, extension: &str, data: Vec<u8>) -> Result<DatafileFile, Error> {
+    match extension {
+        "bmp" => Ok(DatafileFile::Bitmap(data)),
+        "ogg" => Ok(DatafileFile::Vorbis(data)),
+        "xml" => Ok(DatafileFile::Ui(
+            UiTag::parse(String::from_utf8(data)?.as_str())?.post_process(),
+        )),
+        "txt" => {
+            let decr = decrypt_txt(data.into_iter())?;
+            if stem.starts_with("tile_collision") {
+                Ok(DatafileFile::TileCollision(TileCollisionMap::parse(&decr)))
+            } else if stem == "sprites" {
+                Ok(DatafileFile::Sprites(Sprites::parse(decr.as_str())?))
+            } else if stem.starts_with("profile") || stem.starts_with("highscores") {
+                Ok(DatafileFile::Txt(decrypt_exposed_txt(decr)?))
+            } else {
+                Ok(DatafileFile::Txt(decr))
             }
-            "csv" => Ok(DatafileFile::Translations(
-                WINDOWS_1252
-                    .decode(data.as_slice())
-                    .0
-                    .split('\n')
-                    .map(|l| l.trim())
-                    .filter(|l| !l.is_empty())
-                    .map(|l| {
-                        l.splitn(2, ';')
-                            .map(|s| s.to_string())
-                            .collect_tuple::<(String, String)>()
-                            .expect("Invalid csv")
-                    })
-                    .into_group_map(),
-            )),
-            ext => Err(Error::UnknownFormat(ext.to_string())),
         }
+        "csv" => Ok(DatafileFile::Translations(Translations::decode(&data)?)),
+        ext => Err(Error::UnknownFormat(ext.to_string())),
     }
 }
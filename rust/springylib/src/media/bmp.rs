@@ -0,0 +1,285 @@
+use crate::error::Error;
+use crate::media::transparency::TransparencyMode;
+use binrw::prelude::*;
+use std::io::Cursor;
+
+/// Size of the file header in front of the info header
+const FILE_HEADER_SIZE: usize = 14;
+/// Smallest info header with all the fields of [BmpHeader]
+const INFO_HEADER_SIZE: u32 = 40;
+const BI_RGB: u32 = 0;
+const BI_BITFIELDS: u32 = 3;
+/// Masks of 16-bit bitmaps without bit fields, 5 bits per channel
+const DEFAULT_MASKS: [u32; 3] = [0x7c00, 0x03e0, 0x001f];
+
+#[binread]
+#[br(little, magic = b"BM")]
+struct BmpHeader {
+    _file_size: u32,
+    _reserved: u32,
+    data_offset: u32,
+    header_size: u32,
+    width: i32,
+    /// Rows are stored bottom up unless this is negative
+    height: i32,
+    _planes: u16,
+    bit_count: u16,
+    compression: u32,
+    _image_size: u32,
+    _pixels_per_meter: (i32, i32),
+    colors_used: u32,
+    _colors_important: u32,
+}
+
+/// Decoded bitmap
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bitmap {
+    pub width: u32,
+    pub height: u32,
+    /// RGBA, row by row from the top
+    pub data: Vec<u8>,
+}
+
+impl Bitmap {
+    /// Width and height from the header, without decoding the pixels
+    pub fn size(data: &[u8]) -> Result<(u32, u32), Error> {
+        let header = BmpHeader::read(&mut Cursor::new(data))?;
+        Ok((header.width.unsigned_abs(), header.height.unsigned_abs()))
+    }
+
+    /// Decodes a bitmap with magenta as transparent color
+    pub fn decode(data: &[u8]) -> Result<Bitmap, Error> {
+        Bitmap::decode_with(data, TransparencyMode::MAGENTA_KEY)
+    }
+
+    /// Decodes an uncompressed 8-bit palettized, 16-bit or 24-bit bitmap
+    ///
+    /// Palette based transparency only applies to 8-bit bitmaps,
+    /// transparent pixels are set to transparent black.
+    pub fn decode_with(data: &[u8], transparency: TransparencyMode) -> Result<Bitmap, Error> {
+        let header = BmpHeader::read(&mut Cursor::new(data))?;
+        let invalid_data = |info: String| Error::InvalidData {
+            info: Some(info),
+            context: "bmp".to_string(),
+        };
+        if header.header_size < INFO_HEADER_SIZE {
+            return Err(invalid_data(format!(
+                "unsupported header size {}",
+                header.header_size
+            )));
+        }
+
+        let width = header.width.unsigned_abs();
+        let height = header.height.unsigned_abs();
+        let bytes_per_pixel = match (header.bit_count, header.compression) {
+            (8, BI_RGB) | (16, BI_RGB | BI_BITFIELDS) | (24, BI_RGB) => {
+                header.bit_count as usize / 8
+            }
+            (bit_count, compression) => {
+                return Err(invalid_data(format!(
+                    "unsupported {}-bit bitmap with compression {}",
+                    bit_count, compression
+                )))
+            }
+        };
+        let too_large = || invalid_data(format!("{}x{} pixels is too large", width, height));
+        let stride = (width as usize)
+            .checked_mul(bytes_per_pixel)
+            .and_then(|size| size.checked_add(3))
+            .ok_or_else(too_large)?
+            & !3;
+
+        let table_offset = FILE_HEADER_SIZE + header.header_size as usize;
+        let masks = if header.compression == BI_BITFIELDS {
+            let offset = FILE_HEADER_SIZE + INFO_HEADER_SIZE as usize;
+            let mut masks = [0; 3];
+            for (i, mask) in masks.iter_mut().enumerate() {
+                *mask = read_u32(data, offset + i * 4)
+                    .ok_or_else(|| invalid_data("missing bit fields".to_string()))?;
+            }
+            masks
+        } else {
+            DEFAULT_MASKS
+        };
+
+        let palette = if header.bit_count == 8 {
+            let count = match header.colors_used {
+                0 => 256,
+                count => count.min(256) as usize,
+            };
+            let table = data
+                .get(table_offset..table_offset + count * 4)
+                .ok_or_else(|| invalid_data("palette out of bounds".to_string()))?;
+            table
+                .chunks_exact(4)
+                .map(|bgra| [bgra[2], bgra[1], bgra[0], bgra[3]])
+                .collect()
+        } else {
+            vec![]
+        };
+
+        let pixels_start = header.data_offset as usize;
+        let pixels_end = stride
+            .checked_mul(height as usize)
+            .and_then(|size| size.checked_add(pixels_start))
+            .ok_or_else(too_large)?;
+        let pixels = data
+            .get(pixels_start..pixels_end)
+            .ok_or_else(|| invalid_data("pixel data out of bounds".to_string()))?;
+
+        let mut rgba = Vec::with_capacity(width as usize * height as usize * 4);
+        for y in 0..height as usize {
+            let row = if header.height > 0 {
+                height as usize - 1 - y
+            } else {
+                y
+            };
+            let row = &pixels[row * stride..row * stride + width as usize * bytes_per_pixel];
+            for pixel in row.chunks_exact(bytes_per_pixel) {
+                let (color, alpha) = match pixel {
+                    [index] => {
+                        let color = palette.get(*index as usize).copied().unwrap_or_default();
+                        (color, transparency.palette_alpha(*index as usize, color))
+                    }
+                    [low, high] => {
+                        let value = u16::from_le_bytes([*low, *high]) as u32;
+                        let [r, g, b] = masks.map(|mask| channel(value, mask));
+                        ([r, g, b, 0xff], transparency.color_alpha([r, g, b]))
+                    }
+                    [b, g, r] => ([*r, *g, *b, 0xff], transparency.color_alpha([*r, *g, *b])),
+                    _ => unreachable!(),
+                };
+                match alpha {
+                    0 => rgba.extend_from_slice(&[0, 0, 0, 0]),
+                    alpha => rgba.extend_from_slice(&[color[0], color[1], color[2], alpha]),
+                }
+            }
+        }
+
+        Ok(Bitmap {
+            width,
+            height,
+            data: rgba,
+        })
+    }
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+}
+
+/// Extracts a channel and scales it to 8 bits
+fn channel(value: u32, mask: u32) -> u8 {
+    if mask == 0 {
+        return 0;
+    }
+    let max = mask >> mask.trailing_zeros();
+    let value = (value & mask) >> mask.trailing_zeros();
+    (value * 0xff / max) as u8
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::media::bmp::Bitmap;
+    use crate::media::transparency::TransparencyMode;
+
+    /// Bitmap with a 40 byte info header, `extra` goes between header and pixels
+    pub(crate) fn bmp(
+        width: i32,
+        height: i32,
+        bit_count: u16,
+        compression: u32,
+        extra: &[u8],
+        pixels: &[u8],
+    ) -> Vec<u8> {
+        let offset = 14 + 40 + extra.len() as u32;
+        let mut data = vec![];
+        data.extend_from_slice(b"BM");
+        data.extend_from_slice(&(offset + pixels.len() as u32).to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes());
+        data.extend_from_slice(&offset.to_le_bytes());
+        data.extend_from_slice(&40u32.to_le_bytes());
+        data.extend_from_slice(&width.to_le_bytes());
+        data.extend_from_slice(&height.to_le_bytes());
+        data.extend_from_slice(&1u16.to_le_bytes());
+        data.extend_from_slice(&bit_count.to_le_bytes());
+        data.extend_from_slice(&compression.to_le_bytes());
+        data.extend_from_slice(&[0; 20]);
+        data.extend_from_slice(extra);
+        data.extend_from_slice(pixels);
+        data
+    }
+
+    #[test]
+    fn it_should_decode_24_bit() {
+        // bottom up, rows padded to 8 bytes
+        let pixels = [
+            0xff, 0, 0xff, 0, 0, 0xff, 0, 0, //
+            0, 0, 0, 0xff, 0xff, 0xff, 0, 0,
+        ];
+        let data = bmp(2, 2, 24, 0, &[], &pixels);
+        let bitmap = Bitmap::decode(&data).unwrap();
+        assert_eq!((bitmap.width, bitmap.height), (2, 2));
+        assert_eq!(Bitmap::size(&data).unwrap(), (2, 2));
+        assert_eq!(
+            bitmap.data,
+            vec![
+                0, 0, 0, 0xff, 0xff, 0xff, 0xff, 0xff, //
+                0, 0, 0, 0, 0xff, 0, 0, 0xff,
+            ]
+        );
+
+        let opaque = Bitmap::decode_with(&data, TransparencyMode::None).unwrap();
+        assert_eq!(&opaque.data[8..12], &[0xff, 0, 0xff, 0xff]);
+    }
+
+    #[test]
+    fn it_should_decode_8_bit() {
+        let palette = [0xff, 0, 0xff, 0, 0x10, 0x20, 0x30, 0];
+        let mut extra = palette.to_vec();
+        extra.extend_from_slice(&[0; 254 * 4]);
+        // top down
+        let data = bmp(3, -1, 8, 0, &extra, &[0, 1, 1, 0]);
+        let bitmap = Bitmap::decode(&data).unwrap();
+        assert_eq!(
+            bitmap.data,
+            vec![0, 0, 0, 0, 0x30, 0x20, 0x10, 0xff, 0x30, 0x20, 0x10, 0xff]
+        );
+
+        let indexed = Bitmap::decode_with(&data, TransparencyMode::PaletteIndex0).unwrap();
+        assert_eq!(&indexed.data[..4], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn it_should_decode_16_bit() {
+        let pixel = |value: u16| value.to_le_bytes();
+        let data = bmp(2, 1, 16, 0, &[], &[pixel(0x7c00), pixel(0x03e0)].concat());
+        let bitmap = Bitmap::decode_with(&data, TransparencyMode::None).unwrap();
+        assert_eq!(bitmap.data, vec![0xff, 0, 0, 0xff, 0, 0xff, 0, 0xff]);
+
+        let masks = [0xf800u32, 0x07e0, 0x001f]
+            .iter()
+            .flat_map(|mask| mask.to_le_bytes())
+            .collect::<Vec<_>>();
+        let data = bmp(
+            2,
+            1,
+            16,
+            3,
+            &masks,
+            &[pixel(0xf81f), pixel(0x0400)].concat(),
+        );
+        let bitmap = Bitmap::decode(&data).unwrap();
+        assert_eq!(bitmap.data, vec![0, 0, 0, 0, 0, 0x81, 0, 0xff]);
+    }
+
+    #[test]
+    fn it_should_reject_unsupported_bitmaps() {
+        assert!(Bitmap::decode(&bmp(1, 1, 32, 0, &[], &[0; 4])).is_err());
+        assert!(Bitmap::decode(&bmp(1, 1, 8, 1, &[], &[0; 4])).is_err());
+        assert!(Bitmap::decode(&bmp(4, 4, 24, 0, &[], &[0; 4])).is_err());
+        assert!(Bitmap::decode(&bmp(i32::MIN, i32::MIN, 24, 0, &[], &[0; 4])).is_err());
+        assert!(Bitmap::decode(b"PNG").is_err());
+    }
+}
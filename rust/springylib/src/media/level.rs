@@ -1,7 +1,9 @@
+use crate::error::Error;
 use binrw::prelude::*;
 
 #[binrw]
 #[brw(little)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub struct LevelTile {
     pub index: u8,
     pub id: u8,
@@ -9,11 +11,260 @@ pub struct LevelTile {
 
 #[binrw]
 #[brw(little)]
+#[bw(assert(
+    size_error(*width, *height, tiles.len()).is_none(),
+    "{}",
+    size_error(*width, *height, tiles.len()).unwrap_or_default()
+))]
+#[derive(Debug, Clone, PartialEq)]
 pub struct LevelLayer {
     pub tile_count: u32,
     pub width: u32,
+    #[br(assert(area(width, height).is_some(), "{}x{} layer is too large", width, height))]
     pub height: u32,
     pub unknown_2: u32,
-    #[br(count = width * height)]
+    #[br(count = u64::from(width) * u64::from(height))]
     pub tiles: Vec<LevelTile>,
 }
+
+/// Which part of a layer stays in place when resizing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Where the old content starts inside the new size
+    fn offset(&self, old: (u32, u32), new: (u32, u32)) -> (i64, i64) {
+        let (x, y) = match self {
+            Anchor::TopLeft => (0, 0),
+            Anchor::Top => (1, 0),
+            Anchor::TopRight => (2, 0),
+            Anchor::Left => (0, 1),
+            Anchor::Center => (1, 1),
+            Anchor::Right => (2, 1),
+            Anchor::BottomLeft => (0, 2),
+            Anchor::Bottom => (1, 2),
+            Anchor::BottomRight => (2, 2),
+        };
+        (
+            (new.0 as i64 - old.0 as i64) * x / 2,
+            (new.1 as i64 - old.1 as i64) * y / 2,
+        )
+    }
+}
+
+impl LevelLayer {
+    pub fn get(&self, x: u32, y: u32) -> Option<LevelTile> {
+        self.index_of(x, y).map(|i| self.tiles[i])
+    }
+
+    /// Replaces a tile, returning the previous one
+    ///
+    /// Returns [None] and does nothing if the position is outside the layer.
+    pub fn set(&mut self, x: u32, y: u32, tile: LevelTile) -> Option<LevelTile> {
+        let i = self.index_of(x, y)?;
+        Some(std::mem::replace(&mut self.tiles[i], tile))
+    }
+
+    /// Changes the size of the layer, keeping the content at `anchor` in place
+    ///
+    /// New tiles are set to `fill`, tiles outside the new size are dropped.
+    /// Fails without changing anything if there are too many tiles to address.
+    pub fn resize(
+        &mut self,
+        width: u32,
+        height: u32,
+        anchor: Anchor,
+        fill: LevelTile,
+    ) -> Result<(), Error> {
+        let count = area(width, height).ok_or_else(|| Error::InvalidData {
+            info: Some(format!("{}x{} layer is too large", width, height)),
+            context: "level layer".to_string(),
+        })?;
+        let (offset_x, offset_y) = anchor.offset((self.width, self.height), (width, height));
+        let mut tiles = vec![fill; count];
+        for y in 0..height {
+            for x in 0..width {
+                let old_x = x as i64 - offset_x;
+                let old_y = y as i64 - offset_y;
+                if old_x < 0 || old_y < 0 {
+                    continue;
+                }
+                if let Some(tile) = self.get(old_x as u32, old_y as u32) {
+                    tiles[y as usize * width as usize + x as usize] = tile;
+                }
+            }
+        }
+
+        self.width = width;
+        self.height = height;
+        self.tiles = tiles;
+        Ok(())
+    }
+
+    /// Replaces the connected area of tiles equal to the one at `x`, `y`
+    ///
+    /// Tiles are connected horizontally and vertically, not diagonally.
+    /// Returns how many tiles were changed.
+    pub fn flood_fill(&mut self, x: u32, y: u32, tile: LevelTile) -> usize {
+        let Some(target) = self.get(x, y) else {
+            return 0;
+        };
+        if target == tile {
+            return 0;
+        }
+
+        let mut changed = 0;
+        let mut stack = vec![(x, y)];
+        while let Some((x, y)) = stack.pop() {
+            if self.get(x, y) != Some(target) {
+                continue;
+            }
+            self.set(x, y, tile);
+            changed += 1;
+
+            stack.push((x + 1, y));
+            stack.push((x, y + 1));
+            if x > 0 {
+                stack.push((x - 1, y));
+            }
+            if y > 0 {
+                stack.push((x, y - 1));
+            }
+        }
+
+        changed
+    }
+
+    /// Checks that the layer can be written
+    pub fn validate(&self) -> Result<(), Error> {
+        match size_error(self.width, self.height, self.tiles.len()) {
+            Some(info) => Err(Error::InvalidData {
+                info: Some(info),
+                context: "level layer".to_string(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn index_of(&self, x: u32, y: u32) -> Option<usize> {
+        (x < self.width && y < self.height)
+            .then(|| y as usize * self.width as usize + x as usize)
+            .filter(|i| *i < self.tiles.len())
+    }
+}
+
+/// Number of tiles in a layer of this size, [None] if it doesn't fit into a [usize]
+fn area(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize)
+}
+
+/// Why a layer of this size can't hold `found` tiles, if it can't
+fn size_error(width: u32, height: u32, found: usize) -> Option<String> {
+    match area(width, height) {
+        Some(expected) if expected == found => None,
+        Some(expected) => Some(format!(
+            "expected {} tiles for {}x{}, found {}",
+            expected, width, height, found
+        )),
+        None => Some(format!("{}x{} layer is too large", width, height)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::level::{Anchor, LevelLayer, LevelTile};
+    use binrw::{BinRead, BinWrite};
+    use std::io::Cursor;
+
+    fn tile(index: u8) -> LevelTile {
+        LevelTile { index, id: 1 }
+    }
+
+    /// 3x2 layer with tiles numbered row by row
+    fn layer() -> LevelLayer {
+        LevelLayer {
+            tile_count: 256,
+            width: 3,
+            height: 2,
+            unknown_2: 0xdeadbeef,
+            tiles: (0..6).map(tile).collect(),
+        }
+    }
+
+    fn indices(layer: &LevelLayer) -> Vec<u8> {
+        layer.tiles.iter().map(|tile| tile.index).collect()
+    }
+
+    #[test]
+    fn it_should_get_and_set() {
+        let mut layer = layer();
+        assert_eq!(layer.get(2, 1), Some(tile(5)));
+        assert_eq!(layer.get(3, 0), None);
+        assert_eq!(layer.set(1, 1, tile(9)), Some(tile(4)));
+        assert_eq!(layer.get(1, 1), Some(tile(9)));
+        assert_eq!(layer.set(0, 2, tile(9)), None);
+    }
+
+    #[test]
+    fn it_should_resize() {
+        let empty = LevelTile::default();
+
+        let mut grown = layer();
+        grown.resize(5, 4, Anchor::Center, empty).unwrap();
+        assert_eq!(grown.get(1, 1), Some(tile(0)));
+        assert_eq!(grown.get(3, 2), Some(tile(5)));
+        assert_eq!(grown.get(0, 0), Some(empty));
+        assert_eq!(grown.tiles.len(), 20);
+
+        let mut shrunk = layer();
+        shrunk.resize(2, 1, Anchor::BottomRight, empty).unwrap();
+        assert_eq!(indices(&shrunk), vec![4, 5]);
+
+        let mut wider = layer();
+        wider.resize(4, 2, Anchor::TopLeft, empty).unwrap();
+        assert_eq!(indices(&wider), vec![0, 1, 2, 0, 3, 4, 5, 0]);
+        assert_eq!(wider.unknown_2, 0xdeadbeef);
+    }
+
+    #[test]
+    fn it_should_flood_fill() {
+        let mut layer = layer();
+        layer.tiles = [0, 0, 1, 1, 1, 0].into_iter().map(tile).collect();
+        assert_eq!(layer.flood_fill(0, 0, tile(7)), 2);
+        assert_eq!(indices(&layer), vec![7, 7, 1, 1, 1, 0]);
+        assert_eq!(layer.flood_fill(0, 1, tile(0)), 2);
+        assert_eq!(indices(&layer), vec![7, 7, 1, 0, 0, 0]);
+        assert_eq!(layer.flood_fill(0, 0, tile(7)), 0);
+        assert_eq!(layer.flood_fill(5, 5, tile(7)), 0);
+    }
+
+    #[test]
+    fn it_should_validate_before_writing() {
+        let mut layer = layer();
+        assert!(layer.validate().is_ok());
+
+        let mut out = Cursor::new(Vec::new());
+        layer.write(&mut out).unwrap();
+        out.set_position(0);
+        assert_eq!(LevelLayer::read(&mut out).unwrap(), layer);
+
+        layer.tiles.pop();
+        assert!(layer.validate().is_err());
+        assert!(layer.write(&mut Cursor::new(Vec::new())).is_err());
+
+        layer.width = u32::MAX;
+        layer.height = u32::MAX;
+        assert!(layer.validate().is_err());
+        assert!(layer.write(&mut Cursor::new(Vec::new())).is_err());
+    }
+}
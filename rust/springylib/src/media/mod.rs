@@ -1,6 +1,12 @@
+pub mod bmp;
 pub mod font;
 pub mod level;
+pub mod object_script;
+pub mod profile;
 pub mod rle;
 pub mod sprites;
+pub mod tile_collision;
+pub mod translations;
+pub mod transparency;
 pub mod txt;
 pub mod ui;
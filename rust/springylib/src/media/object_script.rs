@@ -0,0 +1,608 @@
+use crate::error::Error;
+use crate::media::txt::{encrypt_txt, TextEncoding};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+pub const OBJECT_START: &str = "OBJECT START";
+pub const DYNAMIC_OBJECT_START: &str = "DYNAMIC OBJECT START";
+
+/// Object scripts, such as `settings/level.txt` or player profiles
+///
+/// ```text
+/// # comment
+/// OBJECT START
+/// class type: "Object"
+/// [LevelSettings] "settings"
+/// {
+///     "levelTime" 300
+///     "scroll speed" 0.5 1.0
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectScript {
+    /// Top level objects in the order they appear in
+    pub objects: Vec<ObjectNode>,
+    /// Lines outside of any object with their line number,
+    /// these are skipped and not written back
+    pub skipped: Vec<(usize, String)>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ObjectNode {
+    /// Started with `DYNAMIC OBJECT START` instead of `OBJECT START`
+    pub dynamic: bool,
+    pub class_type: String,
+    pub resource_type: String,
+    pub name: String,
+    /// Properties in the order they appear in
+    pub props: Vec<(String, PropValue)>,
+    pub children: Vec<ObjectNode>,
+    /// Property values as they were read, see [SourceText]
+    pub source: SourceText,
+}
+
+/// Value text of each property as it was read, by key
+///
+/// `1` and `1.0` read as the same float, so this keeps the original
+/// text for values that haven't changed since. It is ignored when
+/// comparing nodes.
+#[derive(Debug, Clone, Default)]
+pub struct SourceText(pub Vec<(String, String)>);
+
+impl PartialEq for SourceText {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl SourceText {
+    /// Original text of `value`, if it still reads the same
+    pub fn get(&self, key: &str, value: &PropValue) -> Option<&str> {
+        self.0
+            .iter()
+            .filter(|(name, _)| name == key)
+            .map(|(_, text)| text.as_str())
+            .find(|text| text.parse::<PropValue>().ok().as_ref() == Some(value))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PropValue {
+    Int(i32),
+    Float(f32),
+    Vector2(f32, f32),
+    Vector3(f32, f32, f32),
+    String(String),
+}
+
+impl ObjectScript {
+    pub fn parse(string: &str) -> Result<Self, Error> {
+        let mut lines = Lines::new(string);
+        let mut script = ObjectScript::default();
+
+        while let Some((number, line)) = lines.next() {
+            let dynamic = match line {
+                OBJECT_START => false,
+                DYNAMIC_OBJECT_START => true,
+                _ => {
+                    script.skipped.push((number, line.to_string()));
+                    continue;
+                }
+            };
+            script.objects.push(ObjectNode::parse(&mut lines, dynamic)?);
+        }
+
+        Ok(script)
+    }
+
+    pub fn static_objects(&self) -> impl Iterator<Item = &ObjectNode> {
+        self.objects.iter().filter(|object| !object.dynamic)
+    }
+
+    pub fn dynamic_objects(&self) -> impl Iterator<Item = &ObjectNode> {
+        self.objects.iter().filter(|object| object.dynamic)
+    }
+
+    /// Serializes the script, see [ObjectScript::validate]
+    pub fn write(&self) -> Result<String, Error> {
+        self.validate()?;
+        Ok(self.to_string())
+    }
+
+    /// Serializes and encrypts the script, ready to be put into a datafile
+    pub fn to_encrypted(&self, encoding: TextEncoding) -> Result<Vec<u8>, Error> {
+        Ok(encrypt_txt(
+            encoding.encode(self.write()?.as_str()).into_iter(),
+        ))
+    }
+
+    /// Checks that every string can be written
+    ///
+    /// There is no known way to escape quotes or line breaks,
+    /// so strings containing them can't be read back.
+    pub fn validate(&self) -> Result<(), Error> {
+        self.objects.iter().try_for_each(ObjectNode::validate)
+    }
+}
+
+/// Writes the script the way the games do, comments are not preserved
+///
+/// Strings are not checked, use [ObjectScript::write] for that.
+impl Display for ObjectScript {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (i, object) in self.objects.iter().enumerate() {
+            if i > 0 {
+                write!(f, "\r\n")?;
+            }
+            object.write(f, 0)?;
+        }
+        Ok(())
+    }
+}
+
+impl ObjectNode {
+    fn parse(lines: &mut Lines, dynamic: bool) -> Result<Self, Error> {
+        let (number, line) = lines.expect("class type")?;
+        let class_type = line
+            .strip_prefix("class type:")
+            .and_then(|class_type| unquote(class_type.trim()))
+            .ok_or_else(|| invalid_line(number, line, "expected class type"))?;
+
+        let (number, line) = lines.expect("resource type")?;
+        let (resource_type, name) = line
+            .strip_prefix('[')
+            .and_then(|line| line.split_once(']'))
+            .and_then(|(resource_type, name)| Some((resource_type.trim(), unquote(name.trim())?)))
+            .ok_or_else(|| invalid_line(number, line, "expected [resource type] \"name\""))?;
+
+        let (number, line) = lines.expect("{")?;
+        if line != "{" {
+            return Err(invalid_line(number, line, "expected {"));
+        }
+
+        let mut node = ObjectNode {
+            dynamic,
+            class_type: class_type.to_string(),
+            resource_type: resource_type.to_string(),
+            name: name.to_string(),
+            props: vec![],
+            children: vec![],
+            source: SourceText::default(),
+        };
+
+        loop {
+            let (number, line) = lines.expect("}")?;
+            match line {
+                "}" => break,
+                OBJECT_START => node.children.push(ObjectNode::parse(lines, false)?),
+                DYNAMIC_OBJECT_START => node.children.push(ObjectNode::parse(lines, true)?),
+                _ => {
+                    let (key, value) = line
+                        .strip_prefix('"')
+                        .and_then(|line| line.split_once('"'))
+                        .ok_or_else(|| invalid_line(number, line, "expected \"key\""))?;
+                    let text = value.trim();
+                    let value = text
+                        .parse()
+                        .map_err(|_| invalid_line(number, line, "invalid value"))?;
+                    node.props.push((key.to_string(), value));
+                    node.source.0.push((key.to_string(), text.to_string()));
+                }
+            }
+        }
+
+        Ok(node)
+    }
+
+    /// First property with this key
+    pub fn get(&self, key: &str) -> Option<&PropValue> {
+        self.props
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Replaces the first property with this key, or appends it
+    pub fn set(&mut self, key: &str, value: PropValue) {
+        match self.props.iter_mut().find(|(name, _)| name == key) {
+            Some((_, prop)) => *prop = value,
+            None => self.props.push((key.to_string(), value)),
+        }
+    }
+
+    /// First child with this resource type
+    pub fn child(&self, resource_type: &str) -> Option<&ObjectNode> {
+        self.children
+            .iter()
+            .find(|child| child.resource_type == resource_type)
+    }
+}
+
+impl ObjectNode {
+    fn validate(&self) -> Result<(), Error> {
+        let strings = [&self.class_type, &self.resource_type, &self.name]
+            .into_iter()
+            .chain(self.props.iter().flat_map(|(key, value)| match value {
+                PropValue::String(value) => vec![key, value],
+                _ => vec![key],
+            }));
+        for string in strings {
+            if string.contains(['"', '\r', '\n']) {
+                return Err(Error::InvalidData {
+                    info: Some("quotes and line breaks can't be written".to_string()),
+                    context: string.to_string(),
+                });
+            }
+        }
+        self.children.iter().try_for_each(ObjectNode::validate)
+    }
+
+    fn write(&self, f: &mut Formatter<'_>, depth: usize) -> std::fmt::Result {
+        let indent = "    ".repeat(depth);
+        let start = if self.dynamic {
+            DYNAMIC_OBJECT_START
+        } else {
+            OBJECT_START
+        };
+
+        write!(f, "{}{}\r\n", indent, start)?;
+        write!(f, "{}class type: \"{}\"\r\n", indent, self.class_type)?;
+        write!(
+            f,
+            "{}[{}] \"{}\"\r\n",
+            indent, self.resource_type, self.name
+        )?;
+        write!(f, "{}{{\r\n", indent)?;
+        for (key, value) in self.props.iter() {
+            match self.source.get(key, value) {
+                Some(text) => write!(f, "{}    \"{}\" {}\r\n", indent, key, text)?,
+                None => write!(f, "{}    \"{}\" {}\r\n", indent, key, value)?,
+            }
+        }
+        for child in self.children.iter() {
+            child.write(f, depth + 1)?;
+        }
+        write!(f, "{}}}\r\n", indent)
+    }
+}
+
+impl PropValue {
+    /// Numeric value of a scalar
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            PropValue::Int(value) => Some(*value as f32),
+            PropValue::Float(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_i32(&self) -> Option<i32> {
+        match self {
+            PropValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            PropValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+/// Floats are written as short as they parse back to the same value,
+/// whole ones with a `.0` so a single one doesn't parse as an int.
+///
+/// Values read from a file keep their text instead, see [SourceText].
+impl Display for PropValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PropValue::Int(value) => write!(f, "{}", value),
+            PropValue::Float(value) => write!(f, "{}", Float(*value)),
+            PropValue::Vector2(x, y) => write!(f, "{} {}", Float(*x), Float(*y)),
+            PropValue::Vector3(x, y, z) => {
+                write!(f, "{} {} {}", Float(*x), Float(*y), Float(*z))
+            }
+            PropValue::String(value) => write!(f, "\"{}\"", value),
+        }
+    }
+}
+
+struct Float(f32);
+
+impl Display for Float {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.0.fract() == 0.0 {
+            write!(f, "{}.0", self.0)
+        } else {
+            write!(f, "{}", self.0)
+        }
+    }
+}
+
+impl FromStr for PropValue {
+    type Err = std::num::ParseFloatError;
+
+    /// Parses everything after the key, either a quoted string
+    /// or one to three numbers
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        if let Some(string) = unquote(value) {
+            return Ok(PropValue::String(string.to_string()));
+        }
+        if let Ok(int) = value.parse::<i32>() {
+            return Ok(PropValue::Int(int));
+        }
+
+        let values = value
+            .split_whitespace()
+            .map(f32::from_str)
+            .collect::<Result<Vec<f32>, _>>()?;
+        match values[..] {
+            [x] => Ok(PropValue::Float(x)),
+            [x, y] => Ok(PropValue::Vector2(x, y)),
+            [x, y, z] => Ok(PropValue::Vector3(x, y, z)),
+            // let the float parser produce the error
+            _ => value.parse::<f32>().map(PropValue::Float),
+        }
+    }
+}
+
+/// Non-empty lines that aren't comments, with their line number
+struct Lines<'s> {
+    lines: Vec<(usize, &'s str)>,
+    position: usize,
+    last: usize,
+}
+
+impl<'s> Lines<'s> {
+    fn new(string: &'s str) -> Self {
+        let lines = string
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .collect();
+
+        Lines {
+            lines,
+            position: 0,
+            last: string.lines().count(),
+        }
+    }
+
+    fn expect(&mut self, what: &str) -> Result<(usize, &'s str), Error> {
+        self.next().ok_or_else(|| Error::InvalidData {
+            info: Some(format!("line {}: expected {}", self.last, what)),
+            context: "unexpected end of file".to_string(),
+        })
+    }
+}
+
+impl<'s> Iterator for Lines<'s> {
+    type Item = (usize, &'s str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let line = self.lines.get(self.position).copied();
+        self.position += 1;
+        line
+    }
+}
+
+fn invalid_line(number: usize, line: &str, info: &str) -> Error {
+    Error::InvalidData {
+        info: Some(format!("line {}: {}", number, info)),
+        context: line.to_string(),
+    }
+}
+
+fn unquote(string: &str) -> Option<&str> {
+    string.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::media::object_script::{ObjectScript, PropValue};
+    use crate::media::txt::{decrypt_exposed_txt, decrypt_txt, encrypt_exposed_txt, TextEncoding};
+
+    const LEVEL: &str = "# level settings\r\n\
+        OBJECT START\r\n\
+        class type: \"Object\"\r\n\
+        [LevelSettings] \"settings\"\r\n\
+        {\r\n\
+        \x20   \"levelTime\" 300\r\n\
+        \x20   \"gravity\" 9.81\r\n\
+        }\r\n\
+        \r\n\
+        DYNAMIC OBJECT START\r\n\
+        class type: \"Object\"\r\n\
+        [TiledLayer] \"Layer1\"\r\n\
+        {\r\n\
+        \x20   \"is visible\" 1\r\n\
+        \x20   \"scroll speed\" 0.5 1.0\r\n\
+        \x20   \"position\" -1 2.5 3\r\n\
+        }\r\n";
+
+    const PROFILE: &str = "OBJECT START\r\n\
+        class type: \"Object\"\r\n\
+        [PlayerProfile] \"profile\"\r\n\
+        {\r\n\
+        \x20   \"name\" \"Moorhuhn\"\r\n\
+        \x20   \"volumeMusic\" 80\r\n\
+        \x20   OBJECT START\r\n\
+        \x20   class type: \"Object\"\r\n\
+        \x20   [Status] \"\"\r\n\
+        \x20   {\r\n\
+        \x20       \"lives\" 3\r\n\
+        \x20   }\r\n\
+        }\r\n";
+
+    fn error_info(result: Result<ObjectScript, Error>) -> String {
+        match result {
+            Err(Error::InvalidData { info, .. }) => info.unwrap(),
+            other => panic!("expected invalid data, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_should_parse_objects() {
+        let script = ObjectScript::parse(LEVEL).unwrap();
+        assert_eq!(script.objects.len(), 2);
+
+        let settings = script.static_objects().next().unwrap();
+        assert_eq!(settings.class_type, "Object");
+        assert_eq!(settings.resource_type, "LevelSettings");
+        assert_eq!(settings.name, "settings");
+        assert_eq!(settings.get("levelTime"), Some(&PropValue::Int(300)));
+        assert_eq!(settings.get("gravity"), Some(&PropValue::Float(9.81)));
+
+        let layer = script.dynamic_objects().next().unwrap();
+        assert_eq!(layer.name, "Layer1");
+        assert_eq!(
+            layer.get("is visible").and_then(PropValue::as_f32),
+            Some(1.0)
+        );
+        assert_eq!(
+            layer.get("scroll speed"),
+            Some(&PropValue::Vector2(0.5, 1.0))
+        );
+        assert_eq!(
+            layer.get("position"),
+            Some(&PropValue::Vector3(-1.0, 2.5, 3.0))
+        );
+    }
+
+    #[test]
+    fn it_should_parse_children() {
+        let script = ObjectScript::parse(PROFILE).unwrap();
+        let profile = &script.objects[0];
+        assert_eq!(
+            profile.get("name").and_then(PropValue::as_str),
+            Some("Moorhuhn")
+        );
+        assert_eq!(profile.props.len(), 2);
+
+        let status = profile.child("Status").unwrap();
+        assert_eq!(status.name, "");
+        assert_eq!(status.get("lives").and_then(PropValue::as_i32), Some(3));
+    }
+
+    #[test]
+    fn it_should_report_line_numbers() {
+        assert_eq!(
+            error_info(ObjectScript::parse(&LEVEL.replace("9.81", "nine"))),
+            "line 7: invalid value"
+        );
+        assert_eq!(
+            error_info(ObjectScript::parse(
+                &LEVEL.replace("[TiledLayer]", "TiledLayer")
+            )),
+            "line 12: expected [resource type] \"name\""
+        );
+        assert_eq!(
+            error_info(ObjectScript::parse(
+                "OBJECT START\nclass type: \"Object\"\n"
+            )),
+            "line 2: expected resource type"
+        );
+        assert_eq!(
+            error_info(ObjectScript::parse(&PROFILE[..PROFILE.len() - 3])),
+            "line 12: expected }"
+        );
+    }
+
+    #[test]
+    fn it_should_skip_stray_lines() {
+        let script = ObjectScript::parse(&format!("garbage\r\n{}\r\n}}\r\n", PROFILE)).unwrap();
+        assert_eq!(script.objects.len(), 1);
+        assert_eq!(
+            script.skipped,
+            vec![(1, "garbage".to_string()), (16, "}".to_string())]
+        );
+        assert_eq!(script.to_string(), PROFILE);
+    }
+
+    #[test]
+    fn it_should_write_in_original_style() {
+        assert_eq!(ObjectScript::parse(PROFILE).unwrap().to_string(), PROFILE);
+        assert_eq!(
+            ObjectScript::parse(LEVEL).unwrap().to_string(),
+            LEVEL.trim_start_matches("# level settings\r\n")
+        );
+    }
+
+    #[test]
+    fn it_should_round_trip() {
+        for text in [LEVEL, PROFILE] {
+            let script = ObjectScript::parse(text).unwrap();
+            let written = script.to_string();
+            assert_eq!(ObjectScript::parse(&written).unwrap(), script);
+            assert_eq!(ObjectScript::parse(&written).unwrap().to_string(), written);
+        }
+    }
+
+    #[test]
+    fn it_should_write_edited_numbers() {
+        let mut script = ObjectScript::parse(LEVEL).unwrap();
+        script.objects[1].set("position", PropValue::Vector3(-1.0, 2.0, 3.0));
+        script.objects[1].set("scale", PropValue::Vector2(2.0, 0.5));
+
+        let written = script.to_string();
+        assert!(written.contains("\"scroll speed\" 0.5 1.0\r\n"));
+        assert!(written.contains("\"position\" -1.0 2.0 3.0\r\n"));
+        assert!(written.contains("\"scale\" 2.0 0.5\r\n"));
+    }
+
+    #[test]
+    fn it_should_reject_quotes() {
+        let mut script = ObjectScript::parse(PROFILE).unwrap();
+        assert!(script.write().is_ok());
+
+        script.objects[0].set("name", PropValue::String("\"Moorhuhn\"".to_string()));
+        assert!(script.write().is_err());
+        assert!(script.to_encrypted(TextEncoding::Windows1252).is_err());
+
+        script.objects[0].set("name", PropValue::String("Moorhuhn".to_string()));
+        script.objects[0].children[0].name = "line\nbreak".to_string();
+        assert!(script.write().is_err());
+    }
+
+    #[test]
+    fn it_should_round_trip_values() {
+        for value in [
+            PropValue::Int(-42),
+            PropValue::Float(1.0),
+            PropValue::Float(0.1),
+            PropValue::Float(1e-7),
+            PropValue::Vector2(0.5, 1.0),
+            PropValue::Vector3(-1.0, 2.0, 1e10),
+            PropValue::String("Hühnerjäger 2".to_string()),
+            PropValue::String(String::new()),
+        ] {
+            assert_eq!(value.to_string().parse::<PropValue>().unwrap(), value);
+        }
+    }
+
+    #[test]
+    fn it_should_round_trip_edited_scripts() {
+        let mut script = ObjectScript::parse(LEVEL).unwrap();
+        script.objects[0].set("levelTime", PropValue::Int(120));
+        script.objects[1].set("scroll speed", PropValue::Vector2(0.25, 1.0));
+        script.objects[1].set("layer", PropValue::String("Vordergrund".to_string()));
+
+        let encrypted = script.to_encrypted(TextEncoding::Windows1252).unwrap();
+        let decrypted = decrypt_txt(encrypted.into_iter()).unwrap();
+        let parsed = ObjectScript::parse(&decrypted).unwrap();
+        assert_eq!(parsed, script);
+        assert_eq!(
+            parsed.objects[0].get("levelTime"),
+            Some(&PropValue::Int(120))
+        );
+        assert_eq!(parsed.objects[1].props.len(), 4);
+
+        let profile = ObjectScript::parse(PROFILE).unwrap();
+        let exposed = decrypt_exposed_txt(encrypt_exposed_txt(&profile.to_string())).unwrap();
+        assert_eq!(ObjectScript::parse(&exposed).unwrap(), profile);
+    }
+}
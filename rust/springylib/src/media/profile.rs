@@ -0,0 +1,290 @@
+use crate::error::Error;
+use crate::media::object_script::{ObjectNode, ObjectScript, PropValue};
+use crate::media::txt::{decrypt_exposed_txt, encrypt_exposed_txt_with, TextEncoding};
+
+pub const INPUT_COUNT: usize = 7;
+
+/// Player profile as stored in the exposed `profile*.txt` files
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayerProfile {
+    pub name: String,
+    pub volume_music: i32,
+    pub volume_sfx: i32,
+    pub inputs: [InputBinding; INPUT_COUNT],
+    pub current_level: i32,
+    pub game_finished: bool,
+    pub status: Status,
+    pub statistics: Statistics,
+    /// The object this profile was read from, so properties
+    /// we don't know about survive a round trip
+    object: ObjectNode,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct InputBinding {
+    pub key: i32,
+    pub button: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Status {
+    pub lives: i32,
+    pub score: i32,
+    pub bullets: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    pub num_shots_fired: i32,
+    pub num_coins_collected: i32,
+    pub num_diamonds_collected: i32,
+    pub enemies_bonus: i32,
+    pub num_powerups_collected: i32,
+    pub num_gold_statues: i32,
+}
+
+impl Default for PlayerProfile {
+    fn default() -> Self {
+        PlayerProfile {
+            name: String::new(),
+            volume_music: 0,
+            volume_sfx: 0,
+            inputs: Default::default(),
+            current_level: 0,
+            game_finished: false,
+            status: Status::default(),
+            statistics: Statistics::default(),
+            object: ObjectNode {
+                class_type: "Object".to_string(),
+                resource_type: "PlayerProfile".to_string(),
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl PlayerProfile {
+    /// Reads a profile from the contents of an exposed txt file
+    pub fn from_exposed(contents: String) -> Result<Self, Error> {
+        PlayerProfile::parse(decrypt_exposed_txt(contents)?.as_str())
+    }
+
+    /// Reads a profile from an already decrypted object script
+    pub fn parse(string: &str) -> Result<Self, Error> {
+        let script = ObjectScript::parse(string)?;
+        let object = script.objects.first().ok_or(Error::InvalidData {
+            info: Some("empty profile".to_string()),
+            context: string.to_string(),
+        })?;
+        PlayerProfile::from_object(object)
+    }
+
+    pub fn from_object(object: &ObjectNode) -> Result<Self, Error> {
+        let mut inputs = [InputBinding::default(); INPUT_COUNT];
+        for (i, input) in inputs.iter_mut().enumerate() {
+            input.key = int(object, &format!("input {} key", i))?;
+            input.button = int(object, &format!("input {} button", i))?;
+        }
+
+        Ok(PlayerProfile {
+            name: object
+                .get("name")
+                .and_then(PropValue::as_str)
+                .ok_or_else(|| missing(object, "name"))?
+                .to_string(),
+            volume_music: int(object, "volumeMusic")?,
+            volume_sfx: int(object, "volumeSfx")?,
+            inputs,
+            current_level: int(object, "currentLevel")?,
+            game_finished: int(object, "gameFinished")? != 0,
+            status: Status::from_object(child(object, "Status")?)?,
+            statistics: Statistics::from_object(child(object, "Statistics")?)?,
+            object: object.clone(),
+        })
+    }
+
+    pub fn to_object(&self) -> ObjectNode {
+        let mut object = self.object.clone();
+        object.set("name", PropValue::String(self.name.clone()));
+        object.set("volumeMusic", PropValue::Int(self.volume_music));
+        object.set("volumeSfx", PropValue::Int(self.volume_sfx));
+        for (i, input) in self.inputs.iter().enumerate() {
+            object.set(&format!("input {} key", i), PropValue::Int(input.key));
+            object.set(&format!("input {} button", i), PropValue::Int(input.button));
+        }
+        object.set("currentLevel", PropValue::Int(self.current_level));
+        object.set("gameFinished", PropValue::Int(self.game_finished as i32));
+        self.status.write(child_mut(&mut object, "Status"));
+        self.statistics.write(child_mut(&mut object, "Statistics"));
+        object
+    }
+
+    /// Writes the profile as an exposed txt file, the way the games do
+    ///
+    /// Fails if the name can't be written, see [ObjectScript::validate].
+    pub fn to_exposed(&self) -> Result<String, Error> {
+        let script = ObjectScript {
+            objects: vec![self.to_object()],
+            ..Default::default()
+        };
+        Ok(encrypt_exposed_txt_with(
+            &script.write()?,
+            TextEncoding::Windows1252,
+        ))
+    }
+}
+
+impl Status {
+    pub fn from_object(object: &ObjectNode) -> Result<Self, Error> {
+        Ok(Status {
+            lives: int(object, "lives")?,
+            score: int(object, "score")?,
+            bullets: int(object, "bullets")?,
+        })
+    }
+
+    fn write(&self, object: &mut ObjectNode) {
+        object.set("lives", PropValue::Int(self.lives));
+        object.set("score", PropValue::Int(self.score));
+        object.set("bullets", PropValue::Int(self.bullets));
+    }
+}
+
+impl Statistics {
+    pub fn from_object(object: &ObjectNode) -> Result<Self, Error> {
+        Ok(Statistics {
+            num_shots_fired: int(object, "numShotsFired")?,
+            num_coins_collected: int(object, "numCoinsCollected")?,
+            num_diamonds_collected: int(object, "numDiamondsCollected")?,
+            enemies_bonus: int(object, "enemiesBonus")?,
+            num_powerups_collected: int(object, "numPowerupsCollected")?,
+            num_gold_statues: int(object, "numGoldStatues")?,
+        })
+    }
+
+    fn write(&self, object: &mut ObjectNode) {
+        object.set("numShotsFired", PropValue::Int(self.num_shots_fired));
+        object.set(
+            "numCoinsCollected",
+            PropValue::Int(self.num_coins_collected),
+        );
+        object.set(
+            "numDiamondsCollected",
+            PropValue::Int(self.num_diamonds_collected),
+        );
+        object.set("enemiesBonus", PropValue::Int(self.enemies_bonus));
+        object.set(
+            "numPowerupsCollected",
+            PropValue::Int(self.num_powerups_collected),
+        );
+        object.set("numGoldStatues", PropValue::Int(self.num_gold_statues));
+    }
+}
+
+fn missing(object: &ObjectNode, what: &str) -> Error {
+    Error::InvalidData {
+        info: Some(format!("missing {}", what)),
+        context: object.resource_type.clone(),
+    }
+}
+
+fn int(object: &ObjectNode, key: &str) -> Result<i32, Error> {
+    object
+        .get(key)
+        .and_then(PropValue::as_i32)
+        .ok_or_else(|| missing(object, key))
+}
+
+fn child<'a>(object: &'a ObjectNode, resource_type: &str) -> Result<&'a ObjectNode, Error> {
+    object
+        .child(resource_type)
+        .ok_or_else(|| missing(object, resource_type))
+}
+
+/// Child with this resource type, created if it doesn't exist yet
+fn child_mut<'a>(object: &'a mut ObjectNode, resource_type: &str) -> &'a mut ObjectNode {
+    let position = match object
+        .children
+        .iter()
+        .position(|child| child.resource_type == resource_type)
+    {
+        Some(position) => position,
+        None => {
+            object.children.push(ObjectNode {
+                class_type: "Object".to_string(),
+                resource_type: resource_type.to_string(),
+                ..Default::default()
+            });
+            object.children.len() - 1
+        }
+    };
+    &mut object.children[position]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::media::object_script::PropValue;
+    use crate::media::profile::{InputBinding, PlayerProfile};
+    use crate::media::txt::{decrypt_exposed_txt, encrypt_exposed_txt};
+
+    fn profile() -> PlayerProfile {
+        let mut profile = PlayerProfile {
+            name: "Jürgen".to_string(),
+            volume_music: 80,
+            volume_sfx: 100,
+            current_level: 3,
+            game_finished: true,
+            ..Default::default()
+        };
+        profile.inputs[2] = InputBinding { key: 57, button: 1 };
+        profile.status.lives = 3;
+        profile.status.score = 12500;
+        profile.statistics.num_gold_statues = 2;
+        profile
+    }
+
+    #[test]
+    fn it_should_round_trip() {
+        let profile = profile();
+        let exposed = profile.to_exposed().unwrap();
+        assert!(exposed
+            .lines()
+            .all(|line| line.chars().all(|c| c.is_ascii_hexdigit())));
+
+        let read = PlayerProfile::from_exposed(exposed).unwrap();
+        assert_eq!(PlayerProfile::from_object(&read.to_object()).unwrap(), read);
+        assert_eq!(read.name, "Jürgen");
+        assert_eq!(read.inputs[2], InputBinding { key: 57, button: 1 });
+        assert_eq!(read.status, profile.status);
+        assert_eq!(read.statistics, profile.statistics);
+        assert!(read.game_finished);
+    }
+
+    #[test]
+    fn it_should_keep_unknown_properties() {
+        let mut text = decrypt_exposed_txt(profile().to_exposed().unwrap()).unwrap();
+        text = text.replacen("{\r\n", "{\r\n    \"isValid\" 1\r\n", 1);
+
+        let mut read = PlayerProfile::from_exposed(encrypt_exposed_txt(&text)).unwrap();
+        read.current_level = 4;
+        let object = read.to_object();
+        assert_eq!(object.get("isValid"), Some(&PropValue::Int(1)));
+        assert_eq!(object.get("currentLevel"), Some(&PropValue::Int(4)));
+        assert_eq!(object.props[0].0, "isValid");
+    }
+
+    #[test]
+    fn it_should_report_missing_fields() {
+        let text = decrypt_exposed_txt(profile().to_exposed().unwrap())
+            .unwrap()
+            .replace("\"numGoldStatues\" 2", "");
+        match PlayerProfile::parse(&text) {
+            Err(Error::InvalidData { info, context }) => {
+                assert_eq!(info.unwrap(), "missing numGoldStatues");
+                assert_eq!(context, "Statistics");
+            }
+            other => panic!("expected invalid data, got {:?}", other),
+        }
+    }
+}
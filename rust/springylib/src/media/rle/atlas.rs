@@ -0,0 +1,299 @@
+use crate::error::Error;
+use crate::media::rle::RleImage;
+use crate::media::transparency::TransparencyMode;
+
+/// Transparent pixels between frames, so filtering doesn't pick up neighbours
+const PADDING: u32 = 1;
+
+/// Pixel rectangle inside an atlas page
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AtlasRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// RGBA image holding the frames of several animations
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasPage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasFrame {
+    /// Index into [Atlas::pages]
+    pub page: usize,
+    pub region: AtlasRect,
+    /// Position of the region inside the full image
+    pub offset: (u32, u32),
+}
+
+impl AtlasFrame {
+    /// Region in texture coordinates from zero to one as `x, y, width, height`
+    pub fn uv(&self, page: &AtlasPage) -> (f32, f32, f32, f32) {
+        let (width, height) = (page.width as f32, page.height as f32);
+        (
+            self.region.x as f32 / width,
+            self.region.y as f32 / height,
+            self.region.width as f32 / width,
+            self.region.height as f32 / height,
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasAnimation {
+    pub name: String,
+    /// Size of the full image, frames are placed in it by their offset
+    pub width: u32,
+    pub height: u32,
+    pub frames: Vec<AtlasFrame>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Atlas {
+    pub pages: Vec<AtlasPage>,
+    /// In the order they were added
+    pub animations: Vec<AtlasAnimation>,
+}
+
+struct PendingFrame {
+    animation: usize,
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+/// Packs the cropped frames of rle images into as few pages as possible
+pub struct AtlasPacker {
+    max_size: u32,
+    transparency: TransparencyMode,
+    animations: Vec<AtlasAnimation>,
+    frames: Vec<PendingFrame>,
+}
+
+impl AtlasPacker {
+    /// Pages are at most `max_size` pixels wide and high
+    pub fn new(max_size: u32, transparency: TransparencyMode) -> Self {
+        AtlasPacker {
+            max_size,
+            transparency,
+            animations: vec![],
+            frames: vec![],
+        }
+    }
+
+    pub fn add(&mut self, name: &str, image: &RleImage) {
+        let animation = self.animations.len();
+        self.animations.push(AtlasAnimation {
+            name: name.to_string(),
+            width: image.width,
+            height: image.height,
+            frames: image
+                .frames
+                .iter()
+                .map(|frame| AtlasFrame {
+                    page: 0,
+                    region: AtlasRect::default(),
+                    offset: (frame.left, frame.top),
+                })
+                .collect(),
+        });
+        self.frames
+            .extend(image.frames.iter().map(|frame| PendingFrame {
+                animation,
+                width: frame.width,
+                height: frame.height,
+                data: image.get_frame_data_with(frame, self.transparency),
+            }));
+    }
+
+    /// Places the frames on shelves, tallest first
+    ///
+    /// Fails if a single frame is larger than the maximum page size.
+    pub fn pack(self) -> Result<Atlas, Error> {
+        let AtlasPacker {
+            max_size,
+            mut animations,
+            frames,
+            ..
+        } = self;
+
+        let mut frame_indices = vec![];
+        for (animation, frames) in animations.iter().enumerate() {
+            frame_indices.extend((0..frames.frames.len()).map(|i| (animation, i)));
+        }
+        let mut order: Vec<usize> = (0..frames.len()).collect();
+        order.sort_by_key(|i| std::cmp::Reverse(frames[*i].height));
+
+        // used width and height of each page, the current shelf
+        let mut sizes: Vec<(u32, u32)> = vec![];
+        let (mut x, mut y, mut shelf_height) = (0, 0, 0);
+        let mut regions = vec![(0, AtlasRect::default()); frames.len()];
+        for i in order {
+            let frame = &frames[i];
+            if frame.width > max_size || frame.height > max_size {
+                return Err(Error::InvalidData {
+                    info: Some(format!(
+                        "{}x{} frame does not fit into {}x{}",
+                        frame.width, frame.height, max_size, max_size
+                    )),
+                    context: animations[frame.animation].name.clone(),
+                });
+            }
+
+            if sizes.is_empty() || x + frame.width > max_size {
+                x = 0;
+                y += shelf_height + PADDING;
+                shelf_height = 0;
+            }
+            if sizes.is_empty() || y + frame.height > max_size {
+                sizes.push((0, 0));
+                x = 0;
+                y = 0;
+                shelf_height = 0;
+            }
+
+            let page = sizes.len() - 1;
+            regions[i] = (
+                page,
+                AtlasRect {
+                    x,
+                    y,
+                    width: frame.width,
+                    height: frame.height,
+                },
+            );
+            let size = &mut sizes[page];
+            size.0 = size.0.max(x + frame.width);
+            size.1 = size.1.max(y + frame.height);
+            x += frame.width + PADDING;
+            shelf_height = shelf_height.max(frame.height);
+        }
+
+        let mut pages: Vec<AtlasPage> = sizes
+            .into_iter()
+            .map(|(width, height)| AtlasPage {
+                width,
+                height,
+                data: vec![0; width as usize * height as usize * 4],
+            })
+            .collect();
+        for ((frame, (page, region)), (animation, index)) in
+            frames.into_iter().zip(regions).zip(frame_indices)
+        {
+            let target = &mut pages[page];
+            let row_size = region.width as usize * 4;
+            for row in 0..region.height as usize {
+                let start =
+                    ((region.y as usize + row) * target.width as usize + region.x as usize) * 4;
+                target.data[start..start + row_size]
+                    .copy_from_slice(&frame.data[row * row_size..(row + 1) * row_size]);
+            }
+
+            let atlas_frame = &mut animations[animation].frames[index];
+            atlas_frame.page = page;
+            atlas_frame.region = region;
+        }
+
+        Ok(Atlas { pages, animations })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::rle::atlas::{AtlasPacker, AtlasRect};
+    use crate::media::rle::RleImage;
+    use crate::media::transparency::TransparencyMode;
+
+    /// Image with one opaque frame of each size, placed at the bottom right
+    fn image(size: u32, frames: &[(u32, u32)]) -> RleImage {
+        let frames: Vec<Vec<u8>> = frames
+            .iter()
+            .map(|(width, height)| {
+                let mut data = vec![0u8; (size * size * 4) as usize];
+                for y in size - height..size {
+                    for x in size - width..size {
+                        let i = ((y * size + x) * 4) as usize;
+                        data[i..i + 4].copy_from_slice(&[0x40, x as u8, y as u8, 0xff]);
+                    }
+                }
+                data
+            })
+            .collect();
+        RleImage::encode(size, size, &frames).unwrap()
+    }
+
+    #[test]
+    fn it_should_pack_frames() {
+        let mut packer = AtlasPacker::new(16, TransparencyMode::BLACK_KEY);
+        let walk = image(8, &[(2, 3), (4, 5)]);
+        let jump = image(10, &[(1, 1)]);
+        packer.add("walk", &walk);
+        packer.add("jump", &jump);
+        let atlas = packer.pack().unwrap();
+
+        assert_eq!(atlas.pages.len(), 1);
+        let names: Vec<&str> = atlas.animations.iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(names, vec!["walk", "jump"]);
+
+        let walk_frames = &atlas.animations[0].frames;
+        assert_eq!(
+            walk_frames[1].region,
+            AtlasRect {
+                x: 0,
+                y: 0,
+                width: 4,
+                height: 5
+            }
+        );
+        assert_eq!(walk_frames[1].offset, (4, 3));
+        assert_eq!(walk_frames[0].region.x, 5);
+        assert_eq!(atlas.animations[1].frames[0].offset, (9, 9));
+        assert_eq!((atlas.pages[0].width, atlas.pages[0].height), (9, 5));
+
+        // every frame shows the same pixels as the original image
+        for (image, animation) in [walk, jump].iter().zip(atlas.animations.iter()) {
+            for (frame, packed) in image.frames.iter().zip(animation.frames.iter()) {
+                let page = &atlas.pages[packed.page];
+                let full = image.get_image_data(frame);
+                for y in 0..packed.region.height {
+                    for x in 0..packed.region.width {
+                        let source = (((packed.offset.1 + y) * image.width + packed.offset.0 + x)
+                            * 4) as usize;
+                        let target = (((packed.region.y + y) * page.width + packed.region.x + x)
+                            * 4) as usize;
+                        assert_eq!(full[source..source + 4], page.data[target..target + 4]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn it_should_use_multiple_pages() {
+        let mut packer = AtlasPacker::new(8, TransparencyMode::BLACK_KEY);
+        packer.add("big", &image(8, &[(8, 5), (8, 5), (3, 2)]));
+        let atlas = packer.pack().unwrap();
+        assert_eq!(atlas.pages.len(), 2);
+        let pages: Vec<usize> = atlas.animations[0]
+            .frames
+            .iter()
+            .map(|frame| frame.page)
+            .collect();
+        assert_eq!(pages, vec![0, 1, 1]);
+
+        let uv = atlas.animations[0].frames[0].uv(&atlas.pages[0]);
+        assert_eq!(uv, (0.0, 0.0, 1.0, 1.0));
+    }
+
+    #[test]
+    fn it_should_reject_oversized_frames() {
+        let mut packer = AtlasPacker::new(4, TransparencyMode::BLACK_KEY);
+        packer.add("big", &image(8, &[(5, 1)]));
+        assert!(packer.pack().is_err());
+    }
+}
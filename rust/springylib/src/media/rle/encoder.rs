@@ -0,0 +1,319 @@
+use crate::error::Error;
+use crate::media::rle::{RleImage, RleLayer, DEFAULT_FPS};
+use std::collections::HashMap;
+
+/// Pixel data uses a single byte per pixel, so only the first
+/// 256 colors of the color table can be used
+const MAX_COLORS: usize = 256;
+/// Black is transparent, so it is always the first color
+const TRANSPARENT: [u8; 4] = [0, 0, 0, 0];
+/// Pixels with less alpha are transparent
+const ALPHA_THRESHOLD: u8 = 0x80;
+
+impl RleImage {
+    /// Builds an image from RGBA frames of `width` by `height` pixels
+    ///
+    /// All frames share one color table of at most 256 colors, opaque black
+    /// is stored as `#010101` since black is transparent. Every frame is
+    /// cropped to its visible pixels. The header timing is `1 / DEFAULT_FPS`.
+    ///
+    /// [RleImage::hash] is zero. Its algorithm is unknown, nothing has been
+    /// tried to recover it, and it was never checked whether the games load
+    /// files with a zero hash. The games may reject produced files,
+    /// use [RleImage::reencode] to edit an existing image instead.
+    pub fn encode(width: u32, height: u32, frames: &[Vec<u8>]) -> Result<RleImage, Error> {
+        let size = width as usize * height as usize * 4;
+        if let Some(frame) = frames.iter().find(|frame| frame.len() != size) {
+            return Err(Error::InvalidData {
+                info: Some(format!(
+                    "expected {} bytes for {}x{}, found {}",
+                    size,
+                    width,
+                    height,
+                    frame.len()
+                )),
+                context: "rle frame".to_string(),
+            });
+        }
+
+        let mut counts = HashMap::<[u8; 3], usize>::new();
+        for frame in frames {
+            for pixel in frame.chunks_exact(4) {
+                if let Some(color) = opaque_color(pixel) {
+                    *counts.entry(color).or_default() += 1;
+                }
+            }
+        }
+        let palette = quantize(counts.into_iter().collect(), MAX_COLORS - 1);
+
+        let mut color_table = [[0u8; 4]; 512];
+        color_table[0] = TRANSPARENT;
+        for (entry, [r, g, b]) in color_table[1..].iter_mut().zip(palette.iter()) {
+            *entry = [*b, *g, *r, 0xff];
+        }
+
+        let mut lookup = HashMap::<[u8; 3], u8>::new();
+        let mut index_of = |color: [u8; 3]| {
+            *lookup
+                .entry(color)
+                .or_insert_with(|| nearest(&palette, color) as u8 + 1)
+        };
+
+        let frames = frames
+            .iter()
+            .map(|frame| {
+                let (left, top, crop_width, crop_height) = crop(frame, width, height);
+                let mut data = Vec::with_capacity((crop_width * crop_height) as usize);
+                for y in top..top + crop_height {
+                    for x in left..left + crop_width {
+                        let i = (y * width + x) as usize * 4;
+                        data.push(opaque_color(&frame[i..i + 4]).map_or(0, &mut index_of));
+                    }
+                }
+
+                RleLayer {
+                    width: crop_width,
+                    height: crop_height,
+                    left,
+                    top,
+                    numerator: 0,
+                    denominator: 0,
+                    unknown3: 0,
+                    data,
+                }
+            })
+            .collect();
+
+        Ok(RleImage {
+            hash: 0,
+            color_table,
+            width,
+            height,
+            numerator: 1,
+            denominator: DEFAULT_FPS,
+            frames,
+        })
+    }
+
+    /// Replaces the frames of an image with RGBA frames of the same size
+    ///
+    /// Keeps [RleImage::hash] and the header timing of the image.
+    /// If the hash covers the pixel data it won't match the new frames.
+    pub fn reencode(&self, frames: &[Vec<u8>]) -> Result<RleImage, Error> {
+        Ok(RleImage {
+            hash: self.hash,
+            numerator: self.numerator,
+            denominator: self.denominator,
+            ..RleImage::encode(self.width, self.height, frames)?
+        })
+    }
+}
+
+/// Color of a pixel that isn't transparent, moved away from black
+fn opaque_color(pixel: &[u8]) -> Option<[u8; 3]> {
+    if pixel[3] < ALPHA_THRESHOLD {
+        return None;
+    }
+    match [pixel[0], pixel[1], pixel[2]] {
+        [0, 0, 0] => Some([1, 1, 1]),
+        color => Some(color),
+    }
+}
+
+/// Smallest rectangle containing all visible pixels
+///
+/// Frames without any are reduced to a single transparent pixel.
+fn crop(frame: &[u8], width: u32, height: u32) -> (u32, u32, u32, u32) {
+    let visible = |x: u32, y: u32| frame[(y * width + x) as usize * 4 + 3] >= ALPHA_THRESHOLD;
+
+    let mut bounds: Option<(u32, u32, u32, u32)> = None;
+    for y in 0..height {
+        for x in 0..width {
+            if visible(x, y) {
+                bounds = Some(match bounds {
+                    None => (x, y, x, y),
+                    Some((left, top, right, bottom)) => {
+                        (left.min(x), top.min(y), right.max(x), bottom.max(y))
+                    }
+                });
+            }
+        }
+    }
+
+    match bounds {
+        Some((left, top, right, bottom)) => (left, top, right - left + 1, bottom - top + 1),
+        None => (0, 0, width.min(1), height.min(1)),
+    }
+}
+
+/// Reduces colors to at most `max` using median cut, weighted by how often they appear
+fn quantize(colors: Vec<([u8; 3], usize)>, max: usize) -> Vec<[u8; 3]> {
+    if colors.len() <= max {
+        let mut colors: Vec<[u8; 3]> = colors.into_iter().map(|(color, _)| color).collect();
+        colors.sort();
+        return colors;
+    }
+
+    let mut boxes = vec![colors];
+    while boxes.len() < max {
+        let Some((i, channel)) = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, colors)| colors.len() > 1)
+            .map(|(i, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (i, channel, range)
+            })
+            .max_by_key(|(_, _, range)| *range)
+            .map(|(i, channel, _)| (i, channel))
+        else {
+            break;
+        };
+
+        let mut colors = boxes.swap_remove(i);
+        colors.sort_by_key(|(color, _)| color[channel]);
+        let total: usize = colors.iter().map(|(_, count)| count).sum();
+        let mut seen = 0;
+        let median = colors
+            .iter()
+            .position(|(_, count)| {
+                seen += count;
+                seen * 2 >= total
+            })
+            .unwrap_or(0)
+            .clamp(0, colors.len() - 2);
+        let upper = colors.split_off(median + 1);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+
+    boxes
+        .iter()
+        .map(|colors| {
+            let total: usize = colors.iter().map(|(_, count)| count).sum();
+            let mut sum = [0usize; 3];
+            for (color, count) in colors {
+                for channel in 0..3 {
+                    sum[channel] += color[channel] as usize * count;
+                }
+            }
+            sum.map(|value| ((value + total / 2) / total.max(1)) as u8)
+        })
+        .collect()
+}
+
+fn widest_channel(colors: &[([u8; 3], usize)]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let min = colors.iter().map(|(c, _)| c[channel]).min().unwrap_or(0);
+            let max = colors.iter().map(|(c, _)| c[channel]).max().unwrap_or(0);
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
+
+fn nearest(palette: &[[u8; 3]], color: [u8; 3]) -> usize {
+    palette
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, entry)| {
+            (0..3)
+                .map(|channel| (entry[channel] as i32 - color[channel] as i32).pow(2))
+                .sum::<i32>()
+        })
+        .map_or(0, |(i, _)| i)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::rle::encoder::quantize;
+    use crate::media::rle::{encode_rle, parse_rle, RleImage};
+    use binrw::{BinRead, BinWrite, Endian};
+    use std::io::Cursor;
+
+    fn decode_rle(encoded: &[u8], size: usize) -> Vec<u8> {
+        parse_rle(&mut Cursor::new(encoded), Endian::Little, (size as u32,)).unwrap()
+    }
+
+    #[test]
+    fn it_should_round_trip_rle() {
+        let long_literal: Vec<u8> = (0..300).map(|i| i as u8).collect();
+        for data in [
+            vec![],
+            vec![5],
+            vec![1, 2],
+            vec![7; 1000],
+            long_literal,
+            [vec![1, 2, 3], vec![9; 130], vec![4, 4, 5, 5, 5]].concat(),
+        ] {
+            let encoded = encode_rle(&data);
+            assert_eq!(decode_rle(&encoded, data.len()), data);
+        }
+        assert_eq!(encode_rle(&[7; 10]), vec![10, 7]);
+        assert_eq!(encode_rle(&[1, 2, 2]), vec![0xfd, 1, 2, 2]);
+    }
+
+    #[test]
+    fn it_should_encode_frames() {
+        let (width, height) = (4, 3);
+        let mut first = vec![0u8; 4 * 3 * 4];
+        // opaque red at (1, 1), black at (2, 1)
+        first[(4 + 1) * 4..(4 + 1) * 4 + 4].copy_from_slice(&[255, 0, 0, 255]);
+        first[(4 + 2) * 4..(4 + 2) * 4 + 4].copy_from_slice(&[0, 0, 0, 255]);
+        let second = vec![0u8; 4 * 3 * 4];
+
+        let image = RleImage::encode(width, height, &[first, second]).unwrap();
+        assert_eq!(image.frames.len(), 2);
+        let frame = &image.frames[0];
+        assert_eq!(
+            (frame.left, frame.top, frame.width, frame.height),
+            (1, 1, 2, 1)
+        );
+        assert_eq!(image.frames[1].width, 1);
+
+        let mut out = Cursor::new(Vec::new());
+        image.write(&mut out).unwrap();
+        out.set_position(0);
+        let read = RleImage::read(&mut out).unwrap();
+
+        let data = read.get_image_data(&read.frames[0]);
+        assert_eq!(&data[(4 + 1) * 4..(4 + 1) * 4 + 4], &[255, 0, 0, 255]);
+        assert_eq!(&data[(4 + 2) * 4..(4 + 2) * 4 + 4], &[1, 1, 1, 255]);
+        assert_eq!(&data[..4], &[0, 0, 0, 0]);
+        assert!(read
+            .get_image_data(&read.frames[1])
+            .iter()
+            .all(|value| *value == 0));
+    }
+
+    #[test]
+    fn it_should_keep_the_hash_when_reencoding() {
+        let mut image = RleImage::encode(2, 2, &[vec![0; 16]]).unwrap();
+        image.hash = 0x1234_5678_9abc_def0;
+        image.denominator = 10;
+
+        let reencoded = image.reencode(&[vec![0xff; 16], vec![0; 16]]).unwrap();
+        assert_eq!(reencoded.hash, 0x1234_5678_9abc_def0);
+        assert_eq!(reencoded.denominator, 10);
+        assert_eq!(reencoded.frames.len(), 2);
+        assert!(image.reencode(&[vec![0; 15]]).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_wrong_frame_sizes() {
+        assert!(RleImage::encode(2, 2, &[vec![0; 15]]).is_err());
+    }
+
+    #[test]
+    fn it_should_quantize() {
+        let colors: Vec<([u8; 3], usize)> = (0..1000u32)
+            .map(|i| ([(i % 256) as u8, (i / 4) as u8, 0x80], 1))
+            .collect();
+        let palette = quantize(colors.clone(), 255);
+        assert_eq!(palette.len(), 255);
+
+        let exact = quantize(colors[..10].to_vec(), 255);
+        assert_eq!(exact.len(), 10);
+    }
+}
@@ -1,37 +1,58 @@
+use crate::media::transparency::TransparencyMode;
 use binrw::prelude::*;
-use binrw::Endian;
+use binrw::{writer, Endian};
 use std::io::{Read, Seek};
 
+pub mod atlas;
+pub mod encoder;
 #[cfg(all(feature = "rle_gif"))]
 pub mod gif;
 
-#[binread]
-#[br(little, magic = 0x67u32)]
+/// Longest run or literal sequence a single count can describe
+const MAX_RUN: usize = 127;
+/// Frame rate written into the header of encoded images, see [RleImage::numerator]
+pub const DEFAULT_FPS: u32 = 15;
+
+#[binrw]
+#[brw(little, magic = 0x67u32)]
+#[derive(Debug, Clone)]
 pub struct RleImage {
+    /// Unknown algorithm, kept as is when writing
     pub hash: u64,
     pub color_table: [[u8; 4]; 512],
     pub width: u32,
     pub height: u32,
+    /// Possibly the default frame duration in seconds, as `numerator / denominator`
+    ///
+    /// Unverified, no shipped file has been checked against in-game timing,
+    /// so these fields and the ones of [RleLayer] are only kept as they are.
     pub numerator: u32,
     pub denominator: u32,
     #[br(temp)]
+    #[bw(calc = frames.len() as u32)]
     pub frame_count: u32,
     #[br(count = frame_count)]
     pub frames: Vec<RleLayer>,
 }
 
-#[binread]
-#[br(little)]
+#[binrw]
+#[brw(little)]
+#[derive(Debug, Clone)]
 pub struct RleLayer {
     pub width: u32,
     pub height: u32,
     pub left: u32,
     pub top: u32,
+    /// Counterpart of [RleImage::numerator] for this frame
     pub numerator: u32,
     pub denominator: u32,
+    /// Size of the compressed data
+    #[br(temp)]
+    #[bw(calc = encode_rle(data).len() as u32)]
     pub data_size: u32,
     pub unknown3: u32,
     #[br(args(width * height), parse_with = parse_rle)]
+    #[bw(write_with = write_rle)]
     pub data: Vec<u8>,
 }
 
@@ -59,8 +80,79 @@ pub fn parse_rle<R: Read + Seek>(
     Ok(data)
 }
 
+/// Compresses data the way [parse_rle] expects it
+///
+/// A positive count repeats the following byte, a negative
+/// count is followed by that many bytes to copy as they are.
+pub fn encode_rle(data: &[u8]) -> Vec<u8> {
+    let run_at = |i: usize| {
+        data[i..]
+            .iter()
+            .take(MAX_RUN)
+            .take_while(|value| **value == data[i])
+            .count()
+    };
+
+    let mut encoded = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        let run = run_at(i);
+        if run >= 3 {
+            encoded.push(run as u8);
+            encoded.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        let start = i;
+        while i < data.len() && i - start < MAX_RUN && (i == start || run_at(i) < 3) {
+            i += 1;
+        }
+        encoded.push((-((i - start) as i8)) as u8);
+        encoded.extend_from_slice(&data[start..i]);
+    }
+
+    encoded
+}
+
+#[writer(writer)]
+#[allow(clippy::ptr_arg)]
+fn write_rle(data: &Vec<u8>) -> BinResult<()> {
+    writer.write_all(&encode_rle(data))?;
+    Ok(())
+}
+
 impl RleImage {
+    /// Center of the image relative to the top left corner of a cropped frame
+    ///
+    /// Frames are stored without their transparent border, drawing them
+    /// with this as pivot keeps them in place.
+    pub fn frame_pivot(&self, frame: &RleLayer) -> (f32, f32) {
+        (
+            self.width as f32 / 2.0 - frame.left as f32,
+            self.height as f32 / 2.0 - frame.top as f32,
+        )
+    }
+
+    /// Copy with all frames mirrored horizontally
+    pub fn flipped_x(&self) -> RleImage {
+        let mut image = self.clone();
+        for frame in image.frames.iter_mut() {
+            frame.left = self.width.saturating_sub(frame.left + frame.width);
+            for row in frame.data.chunks_exact_mut(frame.width.max(1) as usize) {
+                row.reverse();
+            }
+        }
+        image
+    }
+
+    /// RGBA data of a frame in the full image size, black is transparent
     pub fn get_image_data(&self, layer: &RleLayer) -> Vec<u8> {
+        self.get_image_data_with(layer, TransparencyMode::BLACK_KEY)
+    }
+
+    /// RGBA data of a frame in the full image size
+    pub fn get_image_data_with(&self, layer: &RleLayer, transparency: TransparencyMode) -> Vec<u8> {
         let mut data = Vec::<u8>::with_capacity(self.width as usize * self.height as usize * 4);
         let mut i = 0;
         for y in 0..self.height {
@@ -70,29 +162,70 @@ impl RleImage {
                     || x < layer.left
                     || x >= layer.left + layer.width
                 {
-                    data.push(0);
-                    data.push(0);
-                    data.push(0);
-                    data.push(0);
+                    data.extend_from_slice(&[0, 0, 0, 0]);
                 } else {
-                    let color = self.color_table[layer.data[i] as usize];
+                    data.extend_from_slice(&self.color(layer.data[i], transparency));
                     i += 1;
-                    data.push(color[2]);
-                    data.push(color[1]);
-                    data.push(color[0]);
-                    data.push(if color[2] == 0 && color[1] == 0 && color[0] == 0 {
-                        0
-                    } else {
-                        255
-                    });
                 }
             }
         }
 
         data
     }
+
+    /// RGBA data of a frame in its own, cropped size
+    pub fn get_frame_data_with(&self, layer: &RleLayer, transparency: TransparencyMode) -> Vec<u8> {
+        layer
+            .data
+            .iter()
+            .flat_map(|index| self.color(*index, transparency))
+            .collect()
+    }
+
+    /// Palette entry as RGBA, transparent entries become transparent black
+    fn color(&self, index: u8, transparency: TransparencyMode) -> [u8; 4] {
+        let color = bgra_to_rgba(self.color_table[index as usize]);
+        match transparency.palette_alpha(index as usize, color) {
+            0 => [0, 0, 0, 0],
+            alpha => [color[0], color[1], color[2], alpha],
+        }
+    }
 }
 
 pub fn bgra_to_rgba(pixel: [u8; 4]) -> [u8; 4] {
     [pixel[2], pixel[1], pixel[0], pixel[3]]
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::media::rle::RleImage;
+
+    fn image() -> RleImage {
+        let mut image = RleImage::encode(4, 4, &[vec![0xff; 64], vec![0xff; 64]]).unwrap();
+        image.frames[1].left = 1;
+        image.frames[1].top = 3;
+        image
+    }
+
+    #[test]
+    fn it_should_flip_frames() {
+        let mut frame = vec![0u8; 4 * 4 * 4];
+        frame[4..8].copy_from_slice(&[0xff, 0, 0, 0xff]);
+        frame[8..12].copy_from_slice(&[0, 0xff, 0, 0xff]);
+        let image = RleImage::encode(4, 4, &[frame]).unwrap();
+
+        let flipped = image.flipped_x();
+        assert_eq!(flipped.frames[0].left, 1);
+        let data = flipped.get_image_data(&flipped.frames[0]);
+        assert_eq!(&data[4..8], &[0, 0xff, 0, 0xff]);
+        assert_eq!(&data[8..12], &[0xff, 0, 0, 0xff]);
+        assert_eq!(flipped.flipped_x().frames[0].data, image.frames[0].data);
+    }
+
+    #[test]
+    fn it_should_place_pivots() {
+        let image = image();
+        assert_eq!(image.frame_pivot(&image.frames[0]), (2.0, 2.0));
+        assert_eq!(image.frame_pivot(&image.frames[1]), (1.0, -1.0));
+    }
+}
@@ -1,4 +1,11 @@
 use crate::error::Error;
+use crate::media::bmp::Bitmap;
+use crate::vfs::Vfs;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+/// Extensions of sprite images, in the order they are looked up
+pub const SPRITE_EXTENSIONS: &[&str] = &["bmp", "rle"];
 
 #[derive(Debug)]
 pub struct Sprites {
@@ -56,6 +63,176 @@ impl Sprites {
     }
 }
 
+impl Sprites {
+    /// Writes a sprite set in the format [Sprites::parse] reads
+    pub fn write(sprites: &[Sprites]) -> String {
+        sprites
+            .iter()
+            .map(|sprite| format!("{}\r\n", sprite))
+            .collect()
+    }
+
+    /// Path of the image of this sprite, if it exists
+    ///
+    /// `dir` is the folder containing the `sprites.txt`.
+    pub fn find_file(&self, dir: &str, vfs: &Vfs) -> Option<String> {
+        SPRITE_EXTENSIONS
+            .iter()
+            .map(|ext| format!("{}/sprites/{}.{}", dir, self.file_name, ext))
+            .find(|path| vfs.exists(path))
+    }
+
+    /// Checks a sprite set against the files in `vfs`
+    ///
+    /// `path` is the path of the `sprites.txt`, an empty result means
+    /// all sprites can be loaded.
+    pub fn validate(sprites: &[Sprites], path: &str, vfs: &mut Vfs) -> Vec<SpriteIssue> {
+        let dir = path.rsplit_once(['/', '\\']).map_or("", |(dir, _)| dir);
+        let mut issues = vec![];
+
+        let mut names = HashSet::new();
+        for sprite in sprites {
+            if !names.insert(sprite.name.as_str()) {
+                issues.push(SpriteIssue::DuplicateName(sprite.name.clone()));
+            }
+        }
+
+        for sprite in sprites {
+            let Some(file) = sprite.find_file(dir, vfs) else {
+                issues.push(SpriteIssue::MissingFile {
+                    name: sprite.name.clone(),
+                    file_name: sprite.file_name.clone(),
+                });
+                continue;
+            };
+            let Some(CropMode::FrameCount(frame_count)) = sprite.frames else {
+                continue;
+            };
+            if !file.ends_with(".bmp") {
+                continue;
+            }
+
+            match vfs
+                .read(&file)
+                .map_err(Error::from)
+                .and_then(|data| Bitmap::size(&data))
+            {
+                Ok((_, height)) if frame_count > 0 && height % frame_count as u32 == 0 => {}
+                Ok((_, height)) => issues.push(SpriteIssue::FrameCount {
+                    name: sprite.name.clone(),
+                    frame_count,
+                    height,
+                }),
+                Err(err) => issues.push(SpriteIssue::InvalidBitmap {
+                    name: sprite.name.clone(),
+                    info: err.to_string(),
+                }),
+            }
+        }
+
+        issues
+    }
+}
+
+impl Display for Sprites {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {} {} {}",
+            self.file_name, self.sprite_type, self.name, self.render_mode
+        )?;
+        match &self.frames {
+            Some(CropMode::FrameCount(frame_count)) => write!(f, " {}", frame_count),
+            Some(CropMode::NoCrop) => write!(f, " nocrop"),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Problem found by [Sprites::validate]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpriteIssue {
+    /// More than one entry uses this animation name
+    DuplicateName(String),
+    /// Neither `sprites/<file_name>.bmp` nor `.rle` exist
+    MissingFile {
+        name: String,
+        file_name: String,
+    },
+    /// The height of the bitmap isn't a multiple of the frame count
+    FrameCount {
+        name: String,
+        frame_count: i32,
+        height: u32,
+    },
+    InvalidBitmap {
+        name: String,
+        info: String,
+    },
+}
+
+impl Display for SpriteIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpriteIssue::DuplicateName(name) => write!(f, "{}: duplicate animation name", name),
+            SpriteIssue::MissingFile { name, file_name } => {
+                write!(f, "{}: missing sprites/{}.bmp or .rle", name, file_name)
+            }
+            SpriteIssue::FrameCount {
+                name,
+                frame_count,
+                height,
+            } => write!(
+                f,
+                "{}: {} frames don't divide the bitmap height {}",
+                name, frame_count, height
+            ),
+            SpriteIssue::InvalidBitmap { name, info } => write!(f, "{}: {}", name, info),
+        }
+    }
+}
+
+/// Animation described by an entry of a sprite set
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpriteAnimation {
+    pub name: String,
+    /// Index of the entry whose images are used
+    pub source: usize,
+    /// Images have to be mirrored horizontally
+    pub flip_x: bool,
+}
+
+impl Sprites {
+    /// Resolves the animations of a sprite set
+    ///
+    /// [RenderMode::FlipX] entries use the images of the [RenderMode::NormX]
+    /// entry with the same file, or their own if there is none.
+    pub fn animations(sprites: &[Sprites]) -> Vec<SpriteAnimation> {
+        sprites
+            .iter()
+            .enumerate()
+            .map(|(i, sprite)| match sprite.render_mode {
+                RenderMode::NormX => SpriteAnimation {
+                    name: sprite.name.clone(),
+                    source: i,
+                    flip_x: false,
+                },
+                RenderMode::FlipX => SpriteAnimation {
+                    name: sprite.name.clone(),
+                    source: sprites
+                        .iter()
+                        .position(|source| {
+                            matches!(source.render_mode, RenderMode::NormX)
+                                && source.file_name == sprite.file_name
+                        })
+                        .unwrap_or(i),
+                    flip_x: true,
+                },
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug)]
 pub enum CropMode {
     FrameCount(i32),
@@ -68,9 +245,125 @@ pub enum RenderMode {
     FlipX,
 }
 
+impl Display for RenderMode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RenderMode::NormX => write!(f, "normx"),
+            RenderMode::FlipX => write!(f, "flipx"),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum SpriteType {
     Static,
     Anim,
     AnimRle,
 }
+
+impl Display for SpriteType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SpriteType::Static => write!(f, "static"),
+            SpriteType::Anim => write!(f, "anim"),
+            SpriteType::AnimRle => write!(f, "anim_rle"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::reader::ArchiveReader;
+    use crate::archive::writer::ArchiveWriter;
+    use crate::archive::ArchiveKind;
+    use crate::media::bmp::tests::bmp;
+    use crate::media::sprites::{SpriteAnimation, SpriteIssue, Sprites};
+    use crate::vfs::Vfs;
+    use std::io::{Cursor, Seek};
+
+    const SPRITES: &str = "walk anim_rle walk_right normx\r\n\
+        walk anim_rle walk_left flipx\r\n\
+        coin anim coin normx 4\r\n\
+        logo static logo normx nocrop\r\n";
+
+    fn vfs(coin_height: i32) -> Vfs {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V2, "MHJNR-XXL");
+        writer
+            .add_file("data\\set1\\sprites\\walk.rle", vec![])
+            .unwrap();
+        writer
+            .add_file(
+                "data\\set1\\sprites\\coin.bmp",
+                bmp(32, coin_height, 24, 0, &[], &[]),
+            )
+            .unwrap();
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out).unwrap();
+        out.rewind().unwrap();
+
+        let mut vfs = Vfs::new();
+        vfs.mount(ArchiveReader::new(out).unwrap());
+        vfs
+    }
+
+    #[test]
+    fn it_should_write_sprites() {
+        let sprites = Sprites::parse(SPRITES).unwrap();
+        assert_eq!(Sprites::write(&sprites), SPRITES);
+    }
+
+    #[test]
+    fn it_should_validate_sprites() {
+        let mut sprites = Sprites::parse(SPRITES).unwrap();
+        assert_eq!(
+            Sprites::validate(&sprites, "data/set1/sprites.txt", &mut vfs(128)),
+            vec![SpriteIssue::MissingFile {
+                name: "logo".to_string(),
+                file_name: "logo".to_string(),
+            }]
+        );
+
+        sprites[1].name = "walk_right".to_string();
+        assert_eq!(
+            Sprites::validate(&sprites[..3], "data/set1/sprites.txt", &mut vfs(130)),
+            vec![
+                SpriteIssue::DuplicateName("walk_right".to_string()),
+                SpriteIssue::FrameCount {
+                    name: "coin".to_string(),
+                    frame_count: 4,
+                    height: 130,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn it_should_resolve_flipped_sprites() {
+        let sprites = Sprites::parse(
+            "walk anim_rle walk_right normx\n\
+             walk anim_rle walk_left flipx\n\
+             jump anim_rle jump_left flipx 4\n",
+        )
+        .unwrap();
+        assert_eq!(
+            Sprites::animations(&sprites),
+            vec![
+                SpriteAnimation {
+                    name: "walk_right".to_string(),
+                    source: 0,
+                    flip_x: false,
+                },
+                SpriteAnimation {
+                    name: "walk_left".to_string(),
+                    source: 0,
+                    flip_x: true,
+                },
+                SpriteAnimation {
+                    name: "jump_left".to_string(),
+                    source: 2,
+                    flip_x: true,
+                },
+            ]
+        );
+    }
+}
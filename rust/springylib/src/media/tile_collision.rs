@@ -0,0 +1,110 @@
+/// Collision info for every tile of a tile set, from `tile_collision_XX.txt`
+///
+/// Every tile is a single decimal digit of [TileFlags], in rows
+/// of the tile set, so only values from 0 to 9 can appear.
+/// Anything that isn't a digit is skipped.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TileCollisionMap {
+    pub tiles: Vec<TileFlags>,
+}
+
+/// Collision flags of a single tile
+///
+/// Only [TileFlags::SOLID] is known, it is the bit the Godot port has
+/// always used to make tiles block. The other bits are kept as they
+/// are, what they mean still has to be worked out.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TileFlags(pub u8);
+
+impl TileFlags {
+    pub const EMPTY: TileFlags = TileFlags(0);
+    /// Blocks from all sides
+    pub const SOLID: TileFlags = TileFlags(0x1);
+
+    pub fn contains(&self, flags: TileFlags) -> bool {
+        self.0 & flags.0 == flags.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    /// Collision shape in tile space, from `(0, 0)` at the top left
+    /// to `(1, 1)` at the bottom right, in clockwise order
+    pub fn polygon(&self) -> Option<Vec<(f32, f32)>> {
+        if self.contains(TileFlags::SOLID) {
+            Some(vec![(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+        } else {
+            None
+        }
+    }
+}
+
+impl std::ops::BitOr for TileFlags {
+    type Output = TileFlags;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        TileFlags(self.0 | rhs.0)
+    }
+}
+
+impl TileCollisionMap {
+    pub fn parse(string: &str) -> Self {
+        TileCollisionMap {
+            tiles: string
+                .chars()
+                .filter_map(|c| c.to_digit(10))
+                .map(|digit| TileFlags(digit as u8))
+                .collect(),
+        }
+    }
+
+    /// Flags of the tile at this index, empty if there is none
+    pub fn get(&self, index: usize) -> TileFlags {
+        self.tiles.get(index).copied().unwrap_or_default()
+    }
+
+    /// Collision shape of the tile at this index, see [TileFlags::polygon]
+    pub fn polygon(&self, index: usize) -> Option<Vec<(f32, f32)>> {
+        self.get(index).polygon()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::tile_collision::{TileCollisionMap, TileFlags};
+
+    #[test]
+    fn it_should_parse() {
+        let map = TileCollisionMap::parse("0 1 2\r\n4 8 3\r\n9");
+        assert_eq!(map.tiles.len(), 7);
+        assert_eq!(map.get(0), TileFlags::EMPTY);
+        assert_eq!(map.get(1), TileFlags::SOLID);
+        assert_eq!(map.get(2), TileFlags(2));
+        assert!(map.get(5).contains(TileFlags::SOLID | TileFlags(2)));
+        assert_eq!(map.get(100), TileFlags::EMPTY);
+    }
+
+    #[test]
+    fn it_should_parse_unseparated_rows() {
+        let map = TileCollisionMap::parse("0110\n1001");
+        assert_eq!(map.tiles.len(), 8);
+        assert_eq!(map.get(4), TileFlags::SOLID);
+    }
+
+    #[test]
+    fn it_should_skip_other_characters() {
+        let map = TileCollisionMap::parse("0 1\n1 x;\u{1a}0");
+        assert_eq!(map.tiles.len(), 4);
+        assert_eq!(map.get(3), TileFlags::EMPTY);
+    }
+
+    #[test]
+    fn it_should_build_polygons() {
+        let map = TileCollisionMap::parse("0 1 2 3");
+        assert_eq!(map.polygon(0), None);
+        assert_eq!(map.polygon(1).unwrap().len(), 4);
+        assert_eq!(map.polygon(2), None);
+        assert_eq!(map.polygon(3), map.polygon(1));
+    }
+}
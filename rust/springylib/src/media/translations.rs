@@ -0,0 +1,271 @@
+use crate::error::Error;
+use encoding_rs::WINDOWS_1252;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::iter::Peekable;
+
+/// Translation tables from `.csv` files
+///
+/// Every line is a key followed by its message, separated by `;`.
+/// Fields can be quoted to contain `;`, line breaks or `""` for a
+/// literal quote. Keys that appear multiple times collect all their
+/// messages, one per line.
+///
+/// Only if every line has the same number of three or more fields, the
+/// file is read as one message per language, with empty fields for
+/// missing translations. Otherwise an unquoted `;` is part of the message,
+/// like it was in the single language files the games ship with.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Translations {
+    /// One table per language column, in column order
+    pub locales: Vec<HashMap<String, Vec<String>>>,
+}
+
+impl Translations {
+    /// Decodes a csv file as stored in the datafile
+    pub fn decode(data: &[u8]) -> Result<Self, Error> {
+        Translations::parse(WINDOWS_1252.decode(data).0.as_ref())
+    }
+
+    pub fn parse(string: &str) -> Result<Self, Error> {
+        let records = records(string)?;
+        let columns = records.first().map_or(0, |record| record.fields.len());
+        let multiple = columns > 2 && records.iter().all(|record| record.fields.len() == columns);
+
+        let mut locales = vec![vec![]; if multiple { columns - 1 } else { 1 }];
+        for record in records {
+            let line = record.line;
+            let rest = record.rest;
+            let mut fields = record.fields.into_iter();
+            let key = fields.next().unwrap_or_default();
+            if key.is_empty() || fields.len() == 0 {
+                return Err(Error::InvalidData {
+                    info: Some(format!("line {}: expected key;message", line)),
+                    context: key,
+                });
+            }
+
+            if !multiple {
+                let message = if fields.len() == 1 {
+                    fields.next().unwrap_or_default()
+                } else {
+                    rest
+                };
+                locales[0].push((key, message));
+                continue;
+            }
+            for (locale, message) in locales.iter_mut().zip(fields) {
+                if !message.is_empty() {
+                    locale.push((key.clone(), message));
+                }
+            }
+        }
+
+        Ok(Translations {
+            locales: locales
+                .into_iter()
+                .map(|messages| messages.into_iter().into_group_map())
+                .collect(),
+        })
+    }
+
+    /// Messages of the language in this column, not counting the key
+    pub fn locale(&self, index: usize) -> Option<&HashMap<String, Vec<String>>> {
+        self.locales.get(index)
+    }
+}
+
+/// A csv line, or more if quoted fields contain line breaks
+struct Record {
+    /// Line the record starts on
+    line: usize,
+    fields: Vec<String>,
+    /// Everything after the first `;` as it is written, but trimmed
+    rest: String,
+}
+
+/// Splits csv into records of fields
+///
+/// Blank lines are skipped and line breaks are normalized to `\n`.
+/// Unquoted fields are trimmed, quoted fields are kept as they are.
+fn records(string: &str) -> Result<Vec<Record>, Error> {
+    let mut records = vec![];
+    let mut chars = string.char_indices().peekable();
+    let mut line = 1;
+    let mut start = 1;
+    let mut rest = None;
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+
+    loop {
+        let next = chars.next();
+        let position = next.map_or(string.len(), |(position, _)| position);
+        let c = next.map(|(_, c)| c);
+        match c {
+            Some('"') if !quoted && field.trim().is_empty() => {
+                quoted = true;
+                field = read_quoted(&mut chars, &mut line).ok_or_else(|| Error::InvalidData {
+                    info: Some(format!("line {}: unterminated quote", start)),
+                    context: string.lines().nth(start - 1).unwrap_or("").to_string(),
+                })?;
+            }
+            Some(';') | Some('\n') | None => {
+                let field = std::mem::take(&mut field);
+                fields.push(if quoted {
+                    field
+                } else {
+                    field.trim().to_string()
+                });
+                quoted = false;
+
+                if c == Some(';') {
+                    rest.get_or_insert(position + 1);
+                    continue;
+                }
+                let record = Record {
+                    line: start,
+                    fields: std::mem::take(&mut fields),
+                    rest: rest
+                        .take()
+                        .map_or("", |rest| string[rest..position].trim())
+                        .to_string(),
+                };
+                if record.fields.iter().any(|field| !field.is_empty()) {
+                    records.push(record);
+                }
+                if c.is_none() {
+                    break;
+                }
+                line += 1;
+                start = line;
+            }
+            Some('\r') => {}
+            Some(c) if quoted => {
+                if !c.is_whitespace() {
+                    return Err(Error::InvalidData {
+                        info: Some(format!("line {}: unexpected {} after quote", line, c)),
+                        context: field,
+                    });
+                }
+            }
+            Some(c) => field.push(c),
+        }
+    }
+
+    Ok(records)
+}
+
+/// Reads the rest of a quoted field, [None] if the quote is never closed
+fn read_quoted<I>(chars: &mut Peekable<I>, line: &mut usize) -> Option<String>
+where
+    I: Iterator<Item = (usize, char)>,
+{
+    let mut field = String::new();
+    loop {
+        match chars.next()?.1 {
+            '"' if chars.peek().map(|(_, c)| *c) == Some('"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => return Some(field),
+            '\r' => {}
+            c => {
+                if c == '\n' {
+                    *line += 1;
+                }
+                field.push(c);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::Error;
+    use crate::media::translations::Translations;
+
+    fn error_info(result: Result<Translations, Error>) -> String {
+        match result {
+            Err(Error::InvalidData { info, .. }) => info.unwrap(),
+            other => panic!("expected invalid data, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn it_should_parse_simple_files() {
+        let translations =
+            Translations::parse("start;Spiel starten\r\n\r\nhelp;Zeile 1\r\nhelp;Zeile 2\r\n")
+                .unwrap();
+        let german = translations.locale(0).unwrap();
+        assert_eq!(translations.locales.len(), 1);
+        assert_eq!(german["start"], vec!["Spiel starten"]);
+        assert_eq!(german["help"], vec!["Zeile 1", "Zeile 2"]);
+    }
+
+    #[test]
+    fn it_should_parse_quoted_fields() {
+        let translations = Translations::parse(
+            "quote;\"Er sagte \"\"Hallo\"\"; dann ging er\"\r\n\
+             multi;\"Zeile 1\r\nZeile 2\"\r\n\
+             after; \"padded \" \r\n",
+        )
+        .unwrap();
+        let german = translations.locale(0).unwrap();
+        assert_eq!(german["quote"], vec!["Er sagte \"Hallo\"; dann ging er"]);
+        assert_eq!(german["multi"], vec!["Zeile 1\nZeile 2"]);
+        assert_eq!(german["after"], vec!["padded "]);
+    }
+
+    #[test]
+    fn it_should_parse_multiple_languages() {
+        let translations = Translations::parse(
+            "start;Spiel starten;Start game\r\n\
+             quit;Beenden;\r\n\
+             back;Zur\u{fc}ck;Back\r\n",
+        )
+        .unwrap();
+        assert_eq!(translations.locales.len(), 2);
+        assert_eq!(translations.locale(0).unwrap()["back"], vec!["Zurück"]);
+        assert_eq!(translations.locale(1).unwrap()["start"], vec!["Start game"]);
+        assert!(!translations.locale(1).unwrap().contains_key("quit"));
+        assert!(translations.locale(2).is_none());
+    }
+
+    #[test]
+    fn it_should_keep_semicolons_in_messages() {
+        let translations = Translations::parse(
+            "hint;Tipp: Leertaste; dann Enter\r\n\
+             start;Spiel starten\r\n\
+             quote;\"Er sagte \"\"Hallo\"\"\"\r\n",
+        )
+        .unwrap();
+        let german = translations.locale(0).unwrap();
+        assert_eq!(translations.locales.len(), 1);
+        assert_eq!(german["hint"], vec!["Tipp: Leertaste; dann Enter"]);
+        assert_eq!(german["start"], vec!["Spiel starten"]);
+        assert_eq!(german["quote"], vec!["Er sagte \"Hallo\""]);
+    }
+
+    #[test]
+    fn it_should_decode_windows_1252() {
+        let translations = Translations::decode(b"back;Zur\xfcck\r\n").unwrap();
+        assert_eq!(translations.locale(0).unwrap()["back"], vec!["Zurück"]);
+    }
+
+    #[test]
+    fn it_should_report_bad_lines() {
+        assert_eq!(
+            error_info(Translations::parse("a;b\r\n\r\nbroken\r\n")),
+            "line 3: expected key;message"
+        );
+        assert_eq!(
+            error_info(Translations::parse("a;b\r\nc;\"open\r\nd;e\r\n")),
+            "line 2: unterminated quote"
+        );
+        assert_eq!(
+            error_info(Translations::parse("a;\"b\"c\r\n")),
+            "line 1: unexpected c after quote"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+/// Which pixels of an image become transparent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransparencyMode {
+    /// The fourth byte of a palette entry is used as alpha
+    ///
+    /// In BMP palettes that byte is reserved and normally 0,
+    /// which turns the whole image transparent.
+    PaletteAlpha,
+    /// Colors that differ from `color` by at most `tolerance`
+    /// in every channel are transparent
+    ColorKey { color: [u8; 3], tolerance: u8 },
+    /// The first palette entry is transparent, whatever its color
+    PaletteIndex0,
+    /// Everything is opaque
+    None,
+}
+
+impl TransparencyMode {
+    /// Pure black, how rle sprites have always been decoded
+    pub const BLACK_KEY: TransparencyMode = TransparencyMode::ColorKey {
+        color: [0, 0, 0],
+        tolerance: 0,
+    };
+    /// Pure magenta, used by bitmaps such as tile sets
+    pub const MAGENTA_KEY: TransparencyMode = TransparencyMode::ColorKey {
+        color: [0xff, 0, 0xff],
+        tolerance: 0,
+    };
+
+    /// Alpha of a palette entry, `rgba` as it is stored in the palette
+    pub fn palette_alpha(&self, index: usize, rgba: [u8; 4]) -> u8 {
+        match self {
+            TransparencyMode::PaletteAlpha => rgba[3],
+            TransparencyMode::PaletteIndex0 if index == 0 => 0,
+            TransparencyMode::ColorKey { .. } => self.color_alpha([rgba[0], rgba[1], rgba[2]]),
+            _ => 0xff,
+        }
+    }
+
+    /// Alpha of a color without a palette
+    ///
+    /// Only color keys apply here, other modes keep everything opaque.
+    pub fn color_alpha(&self, rgb: [u8; 3]) -> u8 {
+        match self {
+            TransparencyMode::ColorKey { color, tolerance } => {
+                let keyed = color
+                    .iter()
+                    .zip(rgb.iter())
+                    .all(|(key, value)| key.abs_diff(*value) <= *tolerance);
+                if keyed {
+                    0
+                } else {
+                    0xff
+                }
+            }
+            _ => 0xff,
+        }
+    }
+
+    /// Keys out the pixels of RGBA image data in place
+    ///
+    /// Transparent pixels are set to transparent black, so
+    /// the key color doesn't bleed in when filtering.
+    pub fn apply_rgba(&self, data: &mut [u8]) {
+        for pixel in data.chunks_exact_mut(4) {
+            if self.color_alpha([pixel[0], pixel[1], pixel[2]]) == 0 {
+                pixel.copy_from_slice(&[0, 0, 0, 0]);
+            }
+        }
+    }
+}
+
+/// Transparency of the different kinds of images in a game
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transparency {
+    pub rle: TransparencyMode,
+    pub bmp: TransparencyMode,
+}
+
+impl Default for Transparency {
+    fn default() -> Self {
+        Transparency {
+            rle: TransparencyMode::BLACK_KEY,
+            bmp: TransparencyMode::MAGENTA_KEY,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::transparency::TransparencyMode;
+
+    #[test]
+    fn it_should_key_colors() {
+        let key = TransparencyMode::ColorKey {
+            color: [0xff, 0, 0xff],
+            tolerance: 2,
+        };
+        assert_eq!(key.color_alpha([0xfe, 2, 0xff]), 0);
+        assert_eq!(key.color_alpha([0xfc, 0, 0xff]), 0xff);
+        assert_eq!(
+            TransparencyMode::MAGENTA_KEY.color_alpha([0xfe, 0, 0xff]),
+            0xff
+        );
+
+        let mut data = vec![0xff, 0, 0xff, 0xff, 0xff, 0, 0, 0xff];
+        key.apply_rgba(&mut data);
+        assert_eq!(data, vec![0, 0, 0, 0, 0xff, 0, 0, 0xff]);
+    }
+
+    #[test]
+    fn it_should_use_palettes() {
+        let black = [0, 0, 0, 0x80];
+        assert_eq!(TransparencyMode::PaletteAlpha.palette_alpha(3, black), 0x80);
+        assert_eq!(TransparencyMode::PaletteIndex0.palette_alpha(0, [9; 4]), 0);
+        assert_eq!(
+            TransparencyMode::PaletteIndex0.palette_alpha(1, black),
+            0xff
+        );
+        assert_eq!(TransparencyMode::BLACK_KEY.palette_alpha(1, black), 0);
+        assert_eq!(TransparencyMode::None.palette_alpha(0, black), 0xff);
+    }
+}
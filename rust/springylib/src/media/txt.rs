@@ -1,3 +1,4 @@
+use encoding_rs::WINDOWS_1252;
 use std::fmt::{Display, Formatter};
 use std::num::ParseIntError;
 use std::string::FromUtf8Error;
@@ -31,24 +32,94 @@ impl From<ParseIntError> for DecryptError {
     }
 }
 
+/// How decrypted bytes are turned into a string
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TextEncoding {
+    /// UTF-8 if the text is valid UTF-8, Windows-1252 otherwise
+    #[default]
+    Auto,
+    /// What the original games use
+    Windows1252,
+    Utf8,
+}
+
+impl TextEncoding {
+    pub fn decode(&self, data: Vec<u8>) -> Result<String, DecryptError> {
+        match self {
+            TextEncoding::Utf8 => String::from_utf8(data).map_err(DecryptError::from),
+            TextEncoding::Windows1252 => Ok(WINDOWS_1252.decode(&data).0.into_owned()),
+            TextEncoding::Auto => String::from_utf8(data)
+                .or_else(|err| TextEncoding::Windows1252.decode(err.into_bytes())),
+        }
+    }
+
+    /// Encodes a string with this encoding
+    ///
+    /// [TextEncoding::Auto] prefers Windows-1252 and only falls back to
+    /// UTF-8 if the text contains characters it can't represent.
+    pub fn encode(&self, text: &str) -> Vec<u8> {
+        match self {
+            TextEncoding::Utf8 => text.as_bytes().to_vec(),
+            TextEncoding::Windows1252 => WINDOWS_1252.encode(text).0.into_owned(),
+            TextEncoding::Auto => match WINDOWS_1252.encode(text) {
+                (data, _, false) => data.into_owned(),
+                (_, _, true) => text.as_bytes().to_vec(),
+            },
+        }
+    }
+}
+
 /// Decrypts txt files contained inside the dat file
+///
+/// The encoding is detected automatically, see [decrypt_txt_with]
+/// to force one.
 pub fn decrypt_txt<I>(buffer: I) -> Result<String, DecryptError>
+where
+    I: Iterator<Item = u8>,
+{
+    decrypt_txt_with(buffer, TextEncoding::Auto)
+}
+
+pub fn decrypt_txt_with<I>(buffer: I, encoding: TextEncoding) -> Result<String, DecryptError>
+where
+    I: Iterator<Item = u8>,
+{
+    encoding.decode(decrypt_txt_bytes(buffer))
+}
+
+/// Decrypts txt files without decoding the result
+pub fn decrypt_txt_bytes<I>(buffer: I) -> Vec<u8>
 where
     I: Iterator<Item = u8>,
 {
     let mut key = 0x1234u16;
 
-    String::from_utf8(
-        buffer
-            .map(|char| {
-                let decr = char ^ key as u8;
-                key = key.wrapping_mul(3).wrapping_add(2);
-                decr
-            })
-            .map(|char| (((char >> 1) ^ (char << 1)) & 0x55) ^ (char << 1))
-            .collect(),
-    )
-    .map_err(DecryptError::from)
+    buffer
+        .map(|char| {
+            let decr = char ^ key as u8;
+            key = key.wrapping_mul(3).wrapping_add(2);
+            decr
+        })
+        .map(|char| (((char >> 1) ^ (char << 1)) & 0x55) ^ (char << 1))
+        .collect()
+}
+
+/// Encrypts txt files the same way the games do,
+/// the inverse of [decrypt_txt]
+pub fn encrypt_txt<I>(buffer: I) -> Vec<u8>
+where
+    I: Iterator<Item = u8>,
+{
+    let mut key = 0x1234u16;
+
+    buffer
+        .map(|char| (((char >> 1) ^ (char << 1)) & 0x55) ^ (char << 1))
+        .map(|char| {
+            let encr = char ^ key as u8;
+            key = key.wrapping_mul(3).wrapping_add(2);
+            encr
+        })
+        .collect()
 }
 
 /// Parses a hex string to a Vec<u8>
@@ -66,19 +137,55 @@ fn from_hex(line: &str) -> Result<Vec<u8>, ParseIntError> {
 /// to first be decrypted normally and then again
 /// with this function.
 pub fn decrypt_exposed_txt(contents: String) -> Result<String, DecryptError> {
+    decrypt_exposed_txt_with(contents, TextEncoding::Auto)
+}
+
+pub fn decrypt_exposed_txt_with(
+    contents: String,
+    encoding: TextEncoding,
+) -> Result<String, DecryptError> {
     contents
         .split_terminator("\r\n")
         .map(|line| line.trim())
         .filter(|line| !line.is_empty())
         .map(from_hex)
-        .map(|line| decrypt_txt(line.map_err(DecryptError::from)?.into_iter()))
+        .map(|line| decrypt_txt_with(line.map_err(DecryptError::from)?.into_iter(), encoding))
         .collect::<Result<Vec<String>, _>>()
         .map(|l| l.join("\r\n"))
 }
 
+/// Converts bytes to a lowercase hex string
+fn to_hex(data: &[u8]) -> String {
+    data.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// The inverse of [decrypt_exposed_txt]
+///
+/// Every line is encrypted on its own and written as hex,
+/// each followed by `\r\n`. Empty lines are dropped,
+/// just like the games do.
+pub fn encrypt_exposed_txt(contents: &str) -> String {
+    encrypt_exposed_txt_with(contents, TextEncoding::Auto)
+}
+
+pub fn encrypt_exposed_txt_with(contents: &str, encoding: TextEncoding) -> String {
+    contents
+        .split_terminator("\r\n")
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let encrypted = encrypt_txt(encoding.encode(line).into_iter());
+            format!("{}\r\n", to_hex(&encrypted))
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::media::txt::{decrypt_exposed_txt, decrypt_txt, from_hex};
+    use crate::media::txt::{
+        decrypt_exposed_txt, decrypt_exposed_txt_with, decrypt_txt, decrypt_txt_bytes,
+        decrypt_txt_with, encrypt_exposed_txt, encrypt_exposed_txt_with, encrypt_txt, from_hex,
+        to_hex, TextEncoding,
+    };
 
     #[test]
     fn it_should_parse_hex() {
@@ -98,4 +205,80 @@ mod tests {
             "{\r\n    \"isValid\" 1"
         )
     }
+
+    #[test]
+    fn it_should_write_hex() {
+        assert_eq!(to_hex(&[0xab, 0x0d, 0xef]), "ab0def");
+    }
+
+    #[test]
+    fn it_should_encrypt() {
+        assert_eq!(
+            encrypt_txt("\r\nsound ".bytes()),
+            vec![0x3a, 0x9b, 0x6f, 0x09, 0x7e, 0xd3, 0x74, 0xd6]
+        )
+    }
+
+    #[test]
+    fn it_should_encrypt_exposed() {
+        assert_eq!(
+            encrypt_exposed_txt("{\r\n    \"isValid\" 1"),
+            "83\r\n248ecc86d5d85f6fc6626a6ef5be3e\r\n"
+        )
+    }
+
+    #[test]
+    fn it_should_round_trip() {
+        let text = "OBJECT START\r\n{\r\n    \"name\" \"Moorhuhn\"\r\n}";
+
+        assert_eq!(
+            decrypt_txt(encrypt_txt(text.bytes()).into_iter()).unwrap(),
+            text
+        );
+        assert_eq!(
+            decrypt_exposed_txt(encrypt_exposed_txt(text)).unwrap(),
+            text
+        );
+    }
+
+    #[test]
+    fn it_should_decrypt_windows_1252() {
+        let text = "Hühnerjäger groß";
+        let encrypted = encrypt_txt(TextEncoding::Windows1252.encode(text).into_iter());
+
+        assert_eq!(
+            decrypt_txt_bytes(encrypted.clone().into_iter()),
+            b"H\xfchnerj\xe4ger gro\xdf"
+        );
+        assert_eq!(decrypt_txt(encrypted.clone().into_iter()).unwrap(), text);
+        assert!(decrypt_txt_with(encrypted.into_iter(), TextEncoding::Utf8).is_err());
+    }
+
+    #[test]
+    fn it_should_detect_utf8() {
+        let text = "Hühnerjäger groß";
+        let encrypted = encrypt_txt(text.bytes());
+
+        assert_eq!(decrypt_txt(encrypted.clone().into_iter()).unwrap(), text);
+        assert_eq!(
+            decrypt_txt_with(encrypted.into_iter(), TextEncoding::Windows1252).unwrap(),
+            "HÃ¼hnerjÃ¤ger groÃŸ"
+        );
+    }
+
+    #[test]
+    fn it_should_round_trip_encodings() {
+        let text = "\"name\" \"Jürgen\"\r\n\"score\" 1";
+        for encoding in [
+            TextEncoding::Auto,
+            TextEncoding::Windows1252,
+            TextEncoding::Utf8,
+        ] {
+            let exposed = encrypt_exposed_txt_with(text, encoding);
+            assert_eq!(decrypt_exposed_txt(exposed.clone()).unwrap(), text);
+            assert_eq!(decrypt_exposed_txt_with(exposed, encoding).unwrap(), text);
+        }
+        assert_eq!(TextEncoding::Auto.encode("€"), vec![0x80]);
+        assert_eq!(TextEncoding::Auto.encode("→"), "→".as_bytes());
+    }
 }
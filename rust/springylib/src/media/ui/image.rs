@@ -1,8 +1,8 @@
 use crate::media::ui::vec::deserialize_vec2;
-use crate::media::ui::FadeMode;
+use crate::media::ui::{FadeMode, UiAttributes};
 use serde::Deserialize;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct UiImage {
     pub texture: String,
     #[serde(deserialize_with = "deserialize_vec2")]
@@ -11,6 +11,8 @@ pub struct UiImage {
     pub size: [i32; 2],
     #[serde(rename = "fademode", default)]
     pub fade_mode: FadeMode,
+    #[serde(skip)]
+    pub attributes: UiAttributes,
 }
 
 #[cfg(test)]
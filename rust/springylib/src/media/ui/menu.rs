@@ -1,13 +1,15 @@
-use crate::media::ui::UiTag;
+use crate::media::ui::{UiAttributes, UiTag};
 use serde::Deserialize;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct UiMenu {
     pub selected: String,
     #[serde(rename = "OnBack")]
     pub on_back: Option<String>,
     #[serde(rename = "$value", default)]
     pub children: Vec<UiTag>,
+    #[serde(skip)]
+    pub attributes: UiAttributes,
 }
 
 #[cfg(test)]
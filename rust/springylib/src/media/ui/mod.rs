@@ -9,14 +9,20 @@ use serde::Deserialize;
 
 pub mod image;
 pub mod menu;
+pub mod reader;
 pub mod static_text;
 pub mod text_area;
 pub mod text_button;
 pub mod text_field;
 pub mod toggle_button;
 pub mod vec;
+pub mod writer;
 
-#[derive(Debug, Clone, Deserialize)]
+/// Attributes a tag has no field for, in the order they were read
+pub type UiAttributes = Vec<(String, String)>;
+
+/// Deserializing fails on tags that aren't known, [UiTag::parse] keeps them
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub enum UiTag {
     Menu(UiMenu),
     Image(UiImage),
@@ -25,9 +31,16 @@ pub enum UiTag {
     TextField(UiTextField),
     StaticText(UiStaticText),
     ToggleButton(UiToggleButton),
+    /// Any other element, such as the sliders and list boxes of later games
+    #[serde(skip_deserializing)]
+    Unknown {
+        name: String,
+        attributes: UiAttributes,
+        children: Vec<UiTag>,
+    },
 }
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum HorizontalAlign {
     Left,
@@ -41,10 +54,23 @@ impl Default for HorizontalAlign {
     }
 }
 
-#[derive(Debug, Clone, Deserialize)]
-#[serde(rename_all = "lowercase")]
+impl HorizontalAlign {
+    /// The value as it is written in the `halign` attribute
+    pub fn as_str(&self) -> &str {
+        match self {
+            HorizontalAlign::Left => "left",
+            HorizontalAlign::Center => "center",
+            HorizontalAlign::Right => "right",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(from = "String")]
 pub enum FadeMode {
     None,
+    /// A mode that isn't supported, kept as it was written
+    Other(String),
 }
 
 impl Default for FadeMode {
@@ -53,7 +79,31 @@ impl Default for FadeMode {
     }
 }
 
+impl FadeMode {
+    /// The value as it is written in the `fademode` attribute
+    pub fn as_str(&self) -> &str {
+        match self {
+            FadeMode::None => "none",
+            FadeMode::Other(value) => value,
+        }
+    }
+}
+
+impl From<String> for FadeMode {
+    fn from(value: String) -> Self {
+        match value.as_str() {
+            "none" => FadeMode::None,
+            _ => FadeMode::Other(value),
+        }
+    }
+}
+
 impl UiTag {
+    /// Writes the tag as xml, see [UiTag::flatten]
+    pub fn write(&self) -> String {
+        writer::write_ui(&self.clone().flatten())
+    }
+
     pub fn post_process(mut self) -> Self {
         if let UiTag::Menu(menu) = &mut self {
             let children: Vec<UiTag> = menu.children.drain(..).collect();
@@ -87,6 +137,40 @@ impl UiTag {
 
         self
     }
+
+    /// Undoes [UiTag::post_process]
+    ///
+    /// Text areas in menus are turned back into an opening tag
+    /// followed by their children and an empty closing tag.
+    pub fn flatten(mut self) -> Self {
+        if let UiTag::Menu(menu) = &mut self {
+            let children: Vec<UiTag> = menu.children.drain(..).collect();
+            for child in children {
+                flatten_into(child, &mut menu.children);
+            }
+        }
+
+        self
+    }
+}
+
+fn flatten_into(tag: UiTag, tags: &mut Vec<UiTag>) {
+    match tag.flatten() {
+        UiTag::TextArea(mut area) if !area.is_closing_tag() => {
+            let children: Vec<UiTag> = area.children.drain(..).collect();
+            tags.push(UiTag::TextArea(area));
+            for child in children {
+                flatten_into(child, tags);
+            }
+            tags.push(UiTag::TextArea(UiTextArea {
+                position: None,
+                size: None,
+                children: vec![],
+                attributes: vec![],
+            }));
+        }
+        tag => tags.push(tag),
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,216 @@
+use crate::error::Error;
+use crate::media::ui::image::UiImage;
+use crate::media::ui::menu::UiMenu;
+use crate::media::ui::static_text::UiStaticText;
+use crate::media::ui::text_area::UiTextArea;
+use crate::media::ui::text_button::UiTextButton;
+use crate::media::ui::text_field::UiTextField;
+use crate::media::ui::toggle_button::UiToggleButton;
+use crate::media::ui::{UiAttributes, UiTag};
+use crate::tiled::tmx::escape;
+use xml::reader::{EventReader, XmlEvent};
+
+/// Element as it was read, before it is matched to a [UiTag]
+struct Element {
+    name: String,
+    attributes: UiAttributes,
+    children: Vec<Element>,
+}
+
+impl UiTag {
+    /// Reads a tag and its children
+    ///
+    /// Known tags are read with their derived `Deserialize`, unlike
+    /// `serde_xml_rs::from_str` this keeps tags and attributes there
+    /// is no field for.
+    pub fn parse(xml: &str) -> Result<UiTag, Error> {
+        to_tag(read_element(xml)?)
+    }
+}
+
+fn read_element(xml: &str) -> Result<Element, Error> {
+    let mut stack: Vec<Element> = vec![];
+    for event in EventReader::from_str(xml) {
+        match event? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } => stack.push(Element {
+                name: name.local_name,
+                attributes: attributes
+                    .into_iter()
+                    .map(|attribute| (attribute.name.local_name, attribute.value))
+                    .collect(),
+                children: vec![],
+            }),
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().ok_or(Error::UnknownError)?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Err(Error::InvalidData {
+        info: Some("missing root element".to_string()),
+        context: xml.to_string(),
+    })
+}
+
+fn to_tag(element: Element) -> Result<UiTag, Error> {
+    let children = element
+        .children
+        .into_iter()
+        .map(to_tag)
+        .collect::<Result<Vec<UiTag>, Error>>()?;
+    let xml = empty_tag(&element.name, &element.attributes);
+    let unknown = |fields: &[&str]| -> UiAttributes {
+        element
+            .attributes
+            .iter()
+            .filter(|(key, _)| !fields.contains(&key.as_str()))
+            .cloned()
+            .collect()
+    };
+
+    Ok(match element.name.as_str() {
+        "Menu" => UiTag::Menu(UiMenu {
+            children,
+            attributes: unknown(&["selected", "OnBack"]),
+            ..serde_xml_rs::from_str(&xml)?
+        }),
+        "Image" => UiTag::Image(UiImage {
+            attributes: unknown(&["texture", "position", "size", "fademode"]),
+            ..serde_xml_rs::from_str(&xml)?
+        }),
+        "TextButton" => UiTag::TextButton(UiTextButton {
+            attributes: unknown(&["name", "text", "position", "halign", "fademode", "OnSelect"]),
+            ..serde_xml_rs::from_str(&xml)?
+        }),
+        "TextArea" => UiTag::TextArea(UiTextArea {
+            children,
+            attributes: unknown(&["position", "size"]),
+            ..serde_xml_rs::from_str(&xml)?
+        }),
+        "TextField" => UiTag::TextField(UiTextField {
+            attributes: unknown(&[
+                "name",
+                "text",
+                "position",
+                "bufferVar",
+                "area",
+                "halign",
+                "fademode",
+                "OnSelect",
+            ]),
+            ..serde_xml_rs::from_str(&xml)?
+        }),
+        "StaticText" => UiTag::StaticText(UiStaticText {
+            attributes: unknown(&["text", "position", "halign", "fademode"]),
+            ..serde_xml_rs::from_str(&xml)?
+        }),
+        "ToggleButton" => UiTag::ToggleButton(UiToggleButton {
+            attributes: unknown(&[
+                "name",
+                "text",
+                "position",
+                "value",
+                "minValue",
+                "maxValue",
+                "valueStep",
+                "target",
+                "targetLOffset",
+                "targetROffset",
+                "noSound",
+                "OnChange",
+                "OnSelect",
+            ]),
+            ..serde_xml_rs::from_str(&xml)?
+        }),
+        _ => UiTag::Unknown {
+            name: element.name,
+            attributes: element.attributes,
+            children,
+        },
+    })
+}
+
+/// The element without its children, for the derived `Deserialize`
+fn empty_tag(name: &str, attributes: &UiAttributes) -> String {
+    let attributes: String = attributes
+        .iter()
+        .map(|(key, value)| format!(" {}=\"{}\"", key, escape(value)))
+        .collect();
+    format!("<{}{} />", name, attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::ui::{FadeMode, UiTag};
+
+    // language=xml
+    const XML: &str = "<Menu selected='volume' OnBack='back' music='menu.ogg'> \
+                         <Slider name='volume' position='1,2' range='0,100'> \
+                           <StaticText text='Volume' position='3,4' fademode='slide' /> \
+                         </Slider> \
+                         <CheckBox name='fullscreen' /> \
+                         <Image texture='logo' position='0,0' size='5,6' glow='true' /> \
+                       </Menu>";
+
+    #[test]
+    fn it_should_keep_unknown_tags() {
+        let UiTag::Menu(menu) = UiTag::parse(XML).unwrap() else {
+            panic!();
+        };
+        assert_eq!(menu.selected, "volume");
+        assert_eq!(
+            menu.attributes,
+            vec![("music".to_string(), "menu.ogg".to_string())]
+        );
+
+        let [UiTag::Unknown {
+            name,
+            attributes,
+            children,
+        }, UiTag::Unknown {
+            name: check_box, ..
+        }, UiTag::Image(image)] = menu.children.as_slice()
+        else {
+            panic!("{:?}", menu.children);
+        };
+        assert_eq!(name, "Slider");
+        assert_eq!(check_box, "CheckBox");
+        assert_eq!(
+            attributes,
+            &vec![
+                ("name".to_string(), "volume".to_string()),
+                ("position".to_string(), "1,2".to_string()),
+                ("range".to_string(), "0,100".to_string()),
+            ]
+        );
+        let [UiTag::StaticText(text)] = children.as_slice() else {
+            panic!("{:?}", children);
+        };
+        assert_eq!(text.fade_mode, FadeMode::Other("slide".to_string()));
+        assert_eq!(
+            image.attributes,
+            vec![("glow".to_string(), "true".to_string())]
+        );
+    }
+
+    #[test]
+    fn it_should_write_unknown_tags() {
+        let menu = UiTag::parse(XML).unwrap();
+        let xml = menu.write();
+        assert!(xml.starts_with(
+            "<Menu selected=\"volume\" OnBack=\"back\" music=\"menu.ogg\">\n\
+             \t<Slider name=\"volume\" position=\"1,2\" range=\"0,100\">\n\
+             \t\t<StaticText text=\"Volume\" position=\"3,4\" fademode=\"slide\" />\n\
+             \t</Slider>\n\
+             \t<CheckBox name=\"fullscreen\" />\n"
+        ));
+        assert_eq!(UiTag::parse(&xml).unwrap(), menu);
+    }
+}
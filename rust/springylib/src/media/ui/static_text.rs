@@ -1,8 +1,8 @@
 use crate::media::ui::vec::deserialize_vec2;
-use crate::media::ui::{FadeMode, HorizontalAlign};
+use crate::media::ui::{FadeMode, HorizontalAlign, UiAttributes};
 use serde::Deserialize;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct UiStaticText {
     pub text: String,
     #[serde(deserialize_with = "deserialize_vec2")]
@@ -11,6 +11,8 @@ pub struct UiStaticText {
     pub horizontal_align: HorizontalAlign,
     #[serde(rename = "fademode", default)]
     pub fade_mode: FadeMode,
+    #[serde(skip)]
+    pub attributes: UiAttributes,
 }
 
 #[cfg(test)]
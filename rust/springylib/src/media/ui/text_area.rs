@@ -1,10 +1,10 @@
 use crate::media::ui::vec::deserialize_vec2_opt;
-use crate::media::ui::UiTag;
+use crate::media::ui::{UiAttributes, UiTag};
 use serde::Deserialize;
 
 /// This is a really weird node, sometimes it has children and sometimes, don't ask me why,
 /// it appears as a normal tag and then gets closed by an empty tag of this kind.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct UiTextArea {
     #[serde(deserialize_with = "deserialize_vec2_opt", default)]
     pub position: Option<[i32; 2]>,
@@ -12,6 +12,8 @@ pub struct UiTextArea {
     pub size: Option<[i32; 2]>,
     #[serde(rename = "$value", default)]
     pub children: Vec<UiTag>,
+    #[serde(skip)]
+    pub attributes: UiAttributes,
 }
 
 impl UiTextArea {
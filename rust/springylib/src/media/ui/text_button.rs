@@ -1,8 +1,8 @@
 use crate::media::ui::vec::deserialize_vec2;
-use crate::media::ui::{FadeMode, HorizontalAlign};
+use crate::media::ui::{FadeMode, HorizontalAlign, UiAttributes};
 use serde::Deserialize;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct UiTextButton {
     pub name: Option<String>,
     pub text: String,
@@ -14,6 +14,8 @@ pub struct UiTextButton {
     pub fade_mode: FadeMode,
     #[serde(rename = "OnSelect")]
     pub on_select: String,
+    #[serde(skip)]
+    pub attributes: UiAttributes,
 }
 
 #[cfg(test)]
@@ -1,8 +1,8 @@
 use crate::media::ui::vec::{deserialize_vec2, deserialize_vec4};
-use crate::media::ui::{FadeMode, HorizontalAlign};
+use crate::media::ui::{FadeMode, HorizontalAlign, UiAttributes};
 use serde::Deserialize;
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Deserialize)]
 pub struct UiTextField {
     pub name: Option<String>,
     pub text: String,
@@ -18,6 +18,8 @@ pub struct UiTextField {
     pub fade_mode: FadeMode,
     #[serde(rename = "OnSelect")]
     pub on_select: String,
+    #[serde(skip)]
+    pub attributes: UiAttributes,
 }
 
 #[cfg(test)]
@@ -1,4 +1,5 @@
 use crate::media::ui::vec::deserialize_vec2;
+use crate::media::ui::UiAttributes;
 use serde::Deserialize;
 
 #[derive(Debug, Clone, Deserialize, PartialEq)]
@@ -25,6 +26,8 @@ pub struct UiToggleButton {
     pub on_change: String,
     #[serde(rename = "OnSelect")]
     pub on_select: String,
+    #[serde(skip)]
+    pub attributes: UiAttributes,
 }
 
 #[cfg(test)]
@@ -66,6 +69,7 @@ mod tests {
                 no_sound: false,
                 on_change: "change".to_string(),
                 on_select: "select".to_string(),
+                attributes: vec![],
             }
         )
     }
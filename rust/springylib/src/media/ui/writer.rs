@@ -0,0 +1,286 @@
+use crate::media::ui::image::UiImage;
+use crate::media::ui::menu::UiMenu;
+use crate::media::ui::static_text::UiStaticText;
+use crate::media::ui::text_area::UiTextArea;
+use crate::media::ui::text_button::UiTextButton;
+use crate::media::ui::text_field::UiTextField;
+use crate::media::ui::toggle_button::UiToggleButton;
+use crate::media::ui::{FadeMode, HorizontalAlign, UiAttributes, UiTag};
+use crate::tiled::tmx::escape;
+
+/// Writes tags as they are, without undoing [UiTag::post_process]
+///
+/// Attributes are written in a fixed order, followed by the ones
+/// that were kept from reading. Optional attributes that are
+/// missing or set to their default are left out.
+pub fn write_ui(tag: &UiTag) -> String {
+    let mut xml = String::new();
+    write_tag(&mut xml, tag, 0);
+    xml
+}
+
+fn write_tag(xml: &mut String, tag: &UiTag, depth: usize) {
+    match tag {
+        UiTag::Menu(menu) => write_menu(xml, menu, depth),
+        UiTag::Image(image) => write_image(xml, image, depth),
+        UiTag::TextButton(button) => write_text_button(xml, button, depth),
+        UiTag::TextArea(area) => write_text_area(xml, area, depth),
+        UiTag::TextField(field) => write_text_field(xml, field, depth),
+        UiTag::StaticText(text) => write_static_text(xml, text, depth),
+        UiTag::ToggleButton(toggle) => write_toggle_button(xml, toggle, depth),
+        UiTag::Unknown {
+            name,
+            attributes,
+            children,
+        } => write_element(xml, depth, name, vec![], attributes, children),
+    }
+}
+
+fn write_menu(xml: &mut String, menu: &UiMenu, depth: usize) {
+    let mut attributes = vec![("selected", menu.selected.clone())];
+    if let Some(on_back) = &menu.on_back {
+        attributes.push(("OnBack", on_back.clone()));
+    }
+    write_element(
+        xml,
+        depth,
+        "Menu",
+        attributes,
+        &menu.attributes,
+        &menu.children,
+    );
+}
+
+fn write_image(xml: &mut String, image: &UiImage, depth: usize) {
+    let mut attributes = vec![
+        ("texture", image.texture.clone()),
+        ("position", join(&image.position)),
+        ("size", join(&image.size)),
+    ];
+    push_fade_mode(&mut attributes, &image.fade_mode);
+    write_element(xml, depth, "Image", attributes, &image.attributes, &[]);
+}
+
+fn write_text_button(xml: &mut String, button: &UiTextButton, depth: usize) {
+    let mut attributes = vec![];
+    if let Some(name) = &button.name {
+        attributes.push(("name", name.clone()));
+    }
+    attributes.push(("text", button.text.clone()));
+    attributes.push(("position", join(&button.position)));
+    push_align(&mut attributes, &button.horizontal_align);
+    push_fade_mode(&mut attributes, &button.fade_mode);
+    attributes.push(("OnSelect", button.on_select.clone()));
+    write_element(
+        xml,
+        depth,
+        "TextButton",
+        attributes,
+        &button.attributes,
+        &[],
+    );
+}
+
+fn write_text_area(xml: &mut String, area: &UiTextArea, depth: usize) {
+    let mut attributes = vec![];
+    if let Some(position) = &area.position {
+        attributes.push(("position", join(position)));
+    }
+    if let Some(size) = &area.size {
+        attributes.push(("size", join(size)));
+    }
+    write_element(
+        xml,
+        depth,
+        "TextArea",
+        attributes,
+        &area.attributes,
+        &area.children,
+    );
+}
+
+fn write_text_field(xml: &mut String, field: &UiTextField, depth: usize) {
+    let mut attributes = vec![];
+    if let Some(name) = &field.name {
+        attributes.push(("name", name.clone()));
+    }
+    attributes.push(("text", field.text.clone()));
+    attributes.push(("position", join(&field.position)));
+    attributes.push(("bufferVar", field.buffer_var.clone()));
+    attributes.push(("area", join(&field.area)));
+    push_align(&mut attributes, &field.horizontal_align);
+    push_fade_mode(&mut attributes, &field.fade_mode);
+    attributes.push(("OnSelect", field.on_select.clone()));
+    write_element(xml, depth, "TextField", attributes, &field.attributes, &[]);
+}
+
+fn write_static_text(xml: &mut String, text: &UiStaticText, depth: usize) {
+    let mut attributes = vec![
+        ("text", text.text.clone()),
+        ("position", join(&text.position)),
+    ];
+    push_align(&mut attributes, &text.horizontal_align);
+    push_fade_mode(&mut attributes, &text.fade_mode);
+    write_element(xml, depth, "StaticText", attributes, &text.attributes, &[]);
+}
+
+fn write_toggle_button(xml: &mut String, toggle: &UiToggleButton, depth: usize) {
+    let mut attributes = vec![];
+    if let Some(name) = &toggle.name {
+        attributes.push(("name", name.clone()));
+    }
+    attributes.push(("text", toggle.text.clone()));
+    attributes.push(("position", join(&toggle.position)));
+    attributes.push(("value", toggle.value.clone()));
+    attributes.push(("minValue", toggle.min_value.to_string()));
+    attributes.push(("maxValue", toggle.max_value.to_string()));
+    attributes.push(("valueStep", toggle.value_step.to_string()));
+    attributes.push(("target", toggle.target.clone()));
+    attributes.push(("targetLOffset", join(&toggle.target_l_offset)));
+    attributes.push(("targetROffset", join(&toggle.target_r_offset)));
+    if toggle.no_sound {
+        attributes.push(("noSound", "true".to_string()));
+    }
+    attributes.push(("OnChange", toggle.on_change.clone()));
+    attributes.push(("OnSelect", toggle.on_select.clone()));
+    write_element(
+        xml,
+        depth,
+        "ToggleButton",
+        attributes,
+        &toggle.attributes,
+        &[],
+    );
+}
+
+fn push_align(attributes: &mut Vec<(&str, String)>, align: &HorizontalAlign) {
+    if *align != HorizontalAlign::default() {
+        attributes.push(("halign", align.as_str().to_string()));
+    }
+}
+
+fn push_fade_mode(attributes: &mut Vec<(&str, String)>, fade_mode: &FadeMode) {
+    if *fade_mode != FadeMode::default() {
+        attributes.push(("fademode", fade_mode.as_str().to_string()));
+    }
+}
+
+/// Writes an element with `attributes` followed by `extra`,
+/// its children go one tab deeper
+fn write_element(
+    xml: &mut String,
+    depth: usize,
+    name: &str,
+    attributes: Vec<(&str, String)>,
+    extra: &UiAttributes,
+    children: &[UiTag],
+) {
+    let indent = "\t".repeat(depth);
+    xml.push_str(&format!("{}<{}", indent, name));
+    let extra = extra
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.clone()));
+    for (key, value) in attributes.into_iter().chain(extra) {
+        xml.push_str(&format!(" {}=\"{}\"", key, escape(&value)));
+    }
+
+    if children.is_empty() {
+        xml.push_str(" />\n");
+        return;
+    }
+    xml.push_str(">\n");
+    for child in children {
+        write_tag(xml, child, depth + 1);
+    }
+    xml.push_str(&format!("{}</{}>\n", indent, name));
+}
+
+/// Comma separated, the way vectors are read
+fn join(values: &[i32]) -> String {
+    values
+        .iter()
+        .map(i32::to_string)
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::ui::toggle_button::UiToggleButton;
+    use crate::media::ui::UiTag;
+
+    // language=xml
+    const XML: &str = "<Menu selected='start' OnBack='back'> \
+                         <Image texture='title' position='0,0' size='640,480' /> \
+                         <TextArea position='1,2' size='3,4'/> \
+                            <StaticText position='1,2' text='a &amp; b' halign='center' /> \
+                            <TextButton name='start' text='Start' position='5,6' OnSelect='StartGame' /> \
+                         <TextArea /> \
+                         <TextField text='' position='1,2' bufferVar='name' area='1,2,3,4' OnSelect='ok' /> \
+                       </Menu>";
+
+    fn read(xml: &str) -> UiTag {
+        UiTag::parse(xml).unwrap().post_process()
+    }
+
+    #[test]
+    fn it_should_write_flat_text_areas() {
+        let xml = read(XML).write();
+        assert_eq!(
+            xml,
+            "<Menu selected=\"start\" OnBack=\"back\">\n\
+             \t<Image texture=\"title\" position=\"0,0\" size=\"640,480\" />\n\
+             \t<TextArea position=\"1,2\" size=\"3,4\" />\n\
+             \t<StaticText text=\"a &amp; b\" position=\"1,2\" halign=\"center\" />\n\
+             \t<TextButton name=\"start\" text=\"Start\" position=\"5,6\" OnSelect=\"StartGame\" />\n\
+             \t<TextArea />\n\
+             \t<TextField text=\"\" position=\"1,2\" bufferVar=\"name\" area=\"1,2,3,4\" OnSelect=\"ok\" />\n\
+             </Menu>\n"
+        );
+    }
+
+    #[test]
+    fn it_should_round_trip() {
+        let menu = read(XML);
+        assert_eq!(read(&menu.write()), menu);
+
+        let toggle = UiTag::ToggleButton(UiToggleButton {
+            name: None,
+            text: "Sound".to_string(),
+            position: [1, 2],
+            value: "on".to_string(),
+            min_value: 0,
+            max_value: 10,
+            value_step: 1,
+            target: "volume".to_string(),
+            target_l_offset: [-3, 4],
+            target_r_offset: [5, 6],
+            no_sound: true,
+            on_change: "change".to_string(),
+            on_select: "select".to_string(),
+            attributes: vec![("hint".to_string(), "<b>\"quoted\"</b>".to_string())],
+        });
+        assert_eq!(read(&toggle.write()), toggle);
+    }
+
+    #[test]
+    fn it_should_close_every_area() {
+        // an opening area also ends the one before it
+        let menu = read(
+            "<Menu selected='a'> \
+               <TextArea position='1,2' size='3,4' /> \
+                 <StaticText text='first' position='0,0' /> \
+               <TextArea position='5,6' size='7,8' /> \
+                 <StaticText text='second' position='0,0' /> \
+               <TextArea /> \
+             </Menu>",
+        );
+        if let UiTag::Menu(processed) = &menu {
+            assert_eq!(processed.children.len(), 2);
+        }
+
+        let xml = menu.write();
+        assert_eq!(xml.matches("<TextArea />").count(), 2);
+        assert_eq!(read(&xml), menu);
+    }
+}
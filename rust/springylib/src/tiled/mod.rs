@@ -0,0 +1,366 @@
+use crate::error::Error;
+use crate::media::bmp::Bitmap;
+use crate::media::level::LevelTile;
+use crate::media::object_script::{ObjectNode, ObjectScript, PropValue};
+use crate::media::tile_collision::TileCollisionMap;
+use crate::media::txt::TextEncoding;
+use crate::vfs::Vfs;
+use crate::DatafileFile;
+use binrw::BinWrite;
+use itertools::Itertools;
+use std::io::Cursor;
+
+pub mod tmx;
+pub mod tsx;
+
+/// Tile size of maps without tile sets, and of tile sets whose size
+/// can't be derived from the layers
+pub const DEFAULT_TILE_SIZE: u32 = 32;
+
+/// Settings of a `TiledLayer` object from `settings/level.txt`
+///
+/// In Tiled these are the `visible` and `parallax` attributes of the layer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LayerSettings {
+    pub name: String,
+    pub visible: bool,
+    pub scroll_speed: (f32, f32),
+}
+
+impl LayerSettings {
+    pub fn from_object(object: &ObjectNode) -> Self {
+        LayerSettings {
+            name: object.name.clone(),
+            visible: object.get("is visible").and_then(PropValue::as_f32) != Some(0.0),
+            scroll_speed: match object.get("scroll speed") {
+                Some(PropValue::Vector2(x, y)) => (*x, *y),
+                _ => (1.0, 1.0),
+            },
+        }
+    }
+
+    /// Settings of all tiled layers in a level, in the order they are drawn
+    pub fn from_script(script: &ObjectScript) -> Vec<Self> {
+        script
+            .objects
+            .iter()
+            .filter(|object| object.resource_type == "TiledLayer")
+            .map(LayerSettings::from_object)
+            .collect()
+    }
+
+    /// Writes the settings back into the `TiledLayer` object
+    pub fn apply(&self, object: &mut ObjectNode) {
+        object.set("is visible", PropValue::Int(self.visible as i32));
+        object.set(
+            "scroll speed",
+            PropValue::Vector2(self.scroll_speed.0, self.scroll_speed.1),
+        );
+    }
+}
+
+/// Tile set used by the tiles of a layer with the same [crate::media::level::LevelTile::id]
+///
+/// The image is `set1/sprites/tiles_XX.bmp`, its collision info
+/// `tile_collision_XX.txt` in the level folder, where `XX` is the id plus one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tileset {
+    pub id: u8,
+    /// Width and height of a tile in pixels
+    pub tile_size: u32,
+    pub image_width: u32,
+    pub image_height: u32,
+    pub collision: TileCollisionMap,
+}
+
+impl Tileset {
+    /// File name without extension
+    pub fn name(&self) -> String {
+        tileset_name(self.id)
+    }
+
+    /// Tiles per row of the image
+    pub fn columns(&self) -> u32 {
+        (self.image_width / self.tile_size.max(1)).max(1)
+    }
+
+    /// Tiles per column of the image
+    pub fn rows(&self) -> u32 {
+        (self.image_height / self.tile_size.max(1)).max(1)
+    }
+
+    pub fn tile_count(&self) -> u32 {
+        self.columns() * self.rows()
+    }
+
+    /// Id of a tile set from a file name such as `tiles_02.tsx`
+    pub fn id_from_name(name: &str) -> Option<u8> {
+        let file_name = name.rsplit(['/', '\\']).next()?;
+        let number = file_name
+            .strip_prefix("tiles_")?
+            .split('.')
+            .next()?
+            .parse::<u32>()
+            .ok()?;
+        number.checked_sub(1)?.try_into().ok()
+    }
+}
+
+fn tileset_name(id: u8) -> String {
+    format!("tiles_{:02}", id as u32 + 1)
+}
+
+/// Converts a level into a Tiled map, its tile sets and their images
+///
+/// Returns the files by name, all in the same folder.
+pub fn export_level(vfs: &mut Vfs, level_id: u32) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let level_path = format!("data/level{:02}", level_id);
+    let script = match vfs.load(&format!("{}/settings/level.txt", level_path))? {
+        DatafileFile::Txt(txt) => ObjectScript::parse(&txt)?,
+        _ => return Err(unexpected_file("settings/level.txt")),
+    };
+
+    let mut layers = vec![];
+    for settings in LayerSettings::from_script(&script) {
+        let path = format!("{}/layers/{}.dat", level_path, settings.name.to_lowercase());
+        match vfs.load(&path)? {
+            DatafileFile::Level(layer) => layers.push((settings, layer)),
+            _ => return Err(unexpected_file(&path)),
+        }
+    }
+
+    // tiles that are all zero are empty, id 0 only needs a tile set if it has other tiles
+    let mut files = vec![];
+    let mut tilesets = vec![];
+    let ids = layers
+        .iter()
+        .flat_map(|(_, layer)| layer.tiles.iter())
+        .filter(|tile| **tile != LevelTile::default())
+        .map(|tile| tile.id)
+        .unique()
+        .sorted();
+    for id in ids {
+        let name = tileset_name(id);
+        let image = vfs.read(&format!("data/set1/sprites/{}.bmp", name))?;
+        let (image_width, image_height) = Bitmap::size(&image)?;
+
+        let collision_path = format!("{}/tile_collision_{:02}.txt", level_path, id as u32 + 1);
+        let collision = if vfs.exists(&collision_path) {
+            match vfs.load(&collision_path)? {
+                DatafileFile::TileCollision(collision) => collision,
+                _ => return Err(unexpected_file(&collision_path)),
+            }
+        } else {
+            TileCollisionMap::default()
+        };
+
+        let tile_count = layers
+            .iter()
+            .find(|(_, layer)| layer.tiles.iter().any(|tile| tile.id == id))
+            .map_or(0, |(_, layer)| layer.tile_count);
+        let tileset = Tileset {
+            id,
+            tile_size: tile_size(image_width, tile_count),
+            image_width,
+            image_height,
+            collision,
+        };
+        files.push((
+            format!("{}.tsx", name),
+            tsx::write_tsx(&tileset).into_bytes(),
+        ));
+        files.push((format!("{}.bmp", name), image));
+        tilesets.push(tileset);
+    }
+
+    files.push((
+        format!("level{:02}.tmx", level_id),
+        tmx::write_tmx(&layers, &tilesets).into_bytes(),
+    ));
+    Ok(files)
+}
+
+/// Converts an edited map back into layer files
+///
+/// Visibility and scroll speed are written into the matching objects of
+/// `script`. Returns the layers and the updated `settings/level.txt`
+/// by their path relative to the level folder.
+pub fn import_level(tmx: &str, script: &mut ObjectScript) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let mut files = vec![];
+    for (settings, layer) in tmx::read_tmx(tmx)? {
+        layer.validate()?;
+        let mut data = Cursor::new(Vec::new());
+        layer.write(&mut data)?;
+        files.push((
+            format!("layers/{}.dat", settings.name.to_lowercase()),
+            data.into_inner(),
+        ));
+
+        if let Some(object) = script.objects.iter_mut().find(|object| {
+            object.resource_type == "TiledLayer" && object.name.eq_ignore_ascii_case(&settings.name)
+        }) {
+            settings.apply(object);
+        }
+    }
+
+    files.push((
+        "settings/level.txt".to_string(),
+        script.to_encrypted(TextEncoding::Windows1252)?,
+    ));
+    Ok(files)
+}
+
+/// Tile size of a square tile set image with `tile_count` tiles
+///
+/// Falls back to [DEFAULT_TILE_SIZE] if the tiles don't fill a square grid.
+fn tile_size(image_width: u32, tile_count: u32) -> u32 {
+    let columns = (tile_count as f64).sqrt().round() as u64;
+    let size = u64::from(image_width).checked_div(columns).unwrap_or(0);
+    if size == 0
+        || columns * columns != u64::from(tile_count)
+        || size * columns != u64::from(image_width)
+    {
+        return DEFAULT_TILE_SIZE;
+    }
+    size as u32
+}
+
+fn unexpected_file(path: &str) -> Error {
+    Error::InvalidData {
+        info: Some("unexpected file type".to_string()),
+        context: path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::reader::ArchiveReader;
+    use crate::archive::writer::ArchiveWriter;
+    use crate::archive::ArchiveKind;
+    use crate::media::level::{LevelLayer, LevelTile};
+    use crate::media::object_script::{ObjectScript, PropValue};
+    use crate::media::txt::{decrypt_txt, encrypt_txt};
+    use crate::tiled::{export_level, import_level, Tileset};
+    use crate::vfs::Vfs;
+    use binrw::BinWrite;
+    use std::io::{Cursor, Seek};
+
+    const LEVEL: &str = "OBJECT START\r\n\
+        class type: \"Object\"\r\n\
+        [TiledLayer] \"Background\"\r\n\
+        {\r\n\
+        \x20   \"is visible\" 1\r\n\
+        \x20   \"scroll speed\" 0.5 1.0\r\n\
+        }\r\n";
+
+    fn bmp(width: u32, height: u32) -> Vec<u8> {
+        let mut data = vec![0u8; 0x36];
+        data[..2].copy_from_slice(b"BM");
+        data[18..22].copy_from_slice(&width.to_le_bytes());
+        data[22..26].copy_from_slice(&height.to_le_bytes());
+        data
+    }
+
+    fn layer_data() -> Vec<u8> {
+        let layer = LevelLayer {
+            tile_count: 256,
+            width: 2,
+            height: 2,
+            unknown_2: 7,
+            tiles: vec![
+                LevelTile { index: 0, id: 0 },
+                LevelTile { index: 17, id: 1 },
+                LevelTile { index: 3, id: 2 },
+                LevelTile { index: 255, id: 1 },
+            ],
+        };
+        let mut data = Cursor::new(Vec::new());
+        layer.write(&mut data).unwrap();
+        data.into_inner()
+    }
+
+    fn vfs() -> Vfs {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V2, "MHJNR-XXL");
+        writer
+            .add_file(
+                "data\\level01\\settings\\level.txt",
+                encrypt_txt(LEVEL.bytes()),
+            )
+            .unwrap();
+        writer
+            .add_file("data\\level01\\layers\\background.dat", layer_data())
+            .unwrap();
+        writer
+            .add_file(
+                "data\\level01\\tile_collision_02.txt",
+                encrypt_txt("0 1 2 4".bytes()),
+            )
+            .unwrap();
+        writer
+            .add_file("data\\set1\\sprites\\tiles_02.bmp", bmp(512, 512))
+            .unwrap();
+        writer
+            .add_file("data\\set1\\sprites\\tiles_03.bmp", bmp(256, 256))
+            .unwrap();
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out).unwrap();
+        out.rewind().unwrap();
+
+        let mut vfs = Vfs::new();
+        vfs.mount(ArchiveReader::new(out).unwrap());
+        vfs
+    }
+
+    #[test]
+    fn it_should_name_tilesets() {
+        assert_eq!(Tileset::id_from_name("maps/tiles_02.tsx"), Some(1));
+        assert_eq!(Tileset::id_from_name("tiles_00.tsx"), None);
+        assert_eq!(Tileset::id_from_name("other.tsx"), None);
+    }
+
+    #[test]
+    fn it_should_export_levels() {
+        let files = export_level(&mut vfs(), 1).unwrap();
+        let names: Vec<&str> = files.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "tiles_02.tsx",
+                "tiles_02.bmp",
+                "tiles_03.tsx",
+                "tiles_03.bmp",
+                "level01.tmx"
+            ]
+        );
+
+        let tsx = String::from_utf8(files[0].1.clone()).unwrap();
+        assert!(tsx.contains("tilewidth=\"32\""));
+        assert!(tsx.contains("<polygon points=\"0,0 32,0 32,32 0,32\"/>"));
+        let tmx = String::from_utf8(files[4].1.clone()).unwrap();
+        assert!(tmx.contains("parallaxx=\"0.5\""));
+
+        let tsx = String::from_utf8(files[2].1.clone()).unwrap();
+        assert!(tsx.contains("tilewidth=\"16\""));
+    }
+
+    #[test]
+    fn it_should_import_exported_levels() {
+        let files = export_level(&mut vfs(), 1).unwrap();
+        let tmx = String::from_utf8(files[4].1.clone()).unwrap();
+        let tmx = tmx.replace("parallaxx=\"0.5\"", "parallaxx=\"0.25\" visible=\"0\"");
+
+        let mut script = ObjectScript::parse(LEVEL).unwrap();
+        let imported = import_level(&tmx, &mut script).unwrap();
+        assert_eq!(imported[0].0, "layers/background.dat");
+        assert_eq!(imported[0].1, layer_data());
+
+        assert_eq!(imported[1].0, "settings/level.txt");
+        let level = decrypt_txt(imported[1].1.clone().into_iter()).unwrap();
+        let layer = &ObjectScript::parse(&level).unwrap().objects[0];
+        assert_eq!(layer.get("is visible"), Some(&PropValue::Int(0)));
+        assert_eq!(
+            layer.get("scroll speed"),
+            Some(&PropValue::Vector2(0.25, 1.0))
+        );
+    }
+}
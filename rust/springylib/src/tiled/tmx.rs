@@ -0,0 +1,379 @@
+use crate::error::Error;
+use crate::media::level::{LevelLayer, LevelTile};
+use crate::tiled::{LayerSettings, Tileset, DEFAULT_TILE_SIZE};
+use serde::de::IgnoredAny;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// Tiled stores flipping and rotation in the upper bits of a tile
+const GID_MASK: u32 = 0x1fffffff;
+/// Tiles per tile set if a layer doesn't say otherwise
+const DEFAULT_TILE_COUNT: u32 = 256;
+
+/// Writes layers as a Tiled map that references the tile sets as `.tsx` files
+///
+/// Data Tiled has no place for, like [LevelLayer::unknown_2],
+/// is stored as layer properties. Empty tiles and tiles without a tile set
+/// in `tilesets` are written as gid 0.
+pub fn write_tmx(layers: &[(LayerSettings, LevelLayer)], tilesets: &[Tileset]) -> String {
+    let tile_size = tilesets
+        .first()
+        .map_or(DEFAULT_TILE_SIZE, |tileset| tileset.tile_size);
+    let width = layers
+        .iter()
+        .map(|(_, layer)| layer.width)
+        .max()
+        .unwrap_or(0);
+    let height = layers
+        .iter()
+        .map(|(_, layer)| layer.height)
+        .max()
+        .unwrap_or(0);
+
+    let mut first_gids = HashMap::new();
+    let mut next_gid = 1;
+    let mut tmx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    tmx.push_str(&format!(
+        "<map version=\"1.10\" orientation=\"orthogonal\" renderorder=\"right-down\" width=\"{}\" height=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" infinite=\"0\" nextlayerid=\"{}\" nextobjectid=\"1\">\n",
+        width,
+        height,
+        tile_size,
+        tile_size,
+        layers.len() + 1
+    ));
+    for tileset in tilesets {
+        first_gids.insert(tileset.id, next_gid);
+        tmx.push_str(&format!(
+            " <tileset firstgid=\"{}\" source=\"{}.tsx\"/>\n",
+            next_gid,
+            escape(&tileset.name())
+        ));
+        next_gid += tileset.tile_count();
+    }
+
+    for (i, (settings, layer)) in layers.iter().enumerate() {
+        tmx.push_str(&format!(
+            " <layer id=\"{}\" name=\"{}\" width=\"{}\" height=\"{}\"",
+            i + 1,
+            escape(&settings.name),
+            layer.width,
+            layer.height
+        ));
+        if !settings.visible {
+            tmx.push_str(" visible=\"0\"");
+        }
+        tmx.push_str(&format!(
+            " parallaxx=\"{}\" parallaxy=\"{}\">\n",
+            settings.scroll_speed.0, settings.scroll_speed.1
+        ));
+        tmx.push_str("  <properties>\n");
+        tmx.push_str(&format!(
+            "   <property name=\"tile_count\" type=\"int\" value=\"{}\"/>\n",
+            layer.tile_count
+        ));
+        tmx.push_str(&format!(
+            "   <property name=\"unknown_2\" type=\"int\" value=\"{}\"/>\n",
+            layer.unknown_2
+        ));
+        tmx.push_str("  </properties>\n");
+
+        let rows = layer
+            .tiles
+            .chunks(layer.width.max(1) as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|tile| match first_gids.get(&tile.id) {
+                        Some(first_gid) if *tile != LevelTile::default() => {
+                            first_gid + tile.index as u32
+                        }
+                        _ => 0,
+                    })
+                    .map(|gid| gid.to_string())
+                    .collect::<Vec<_>>()
+                    .join(",")
+            })
+            .collect::<Vec<_>>()
+            .join(",\n");
+        tmx.push_str(&format!("  <data encoding=\"csv\">\n{}\n</data>\n", rows));
+        tmx.push_str(" </layer>\n");
+    }
+
+    tmx.push_str("</map>\n");
+    tmx
+}
+
+/// Reads the tile layers of a Tiled map written by [write_tmx]
+///
+/// Tile sets have to keep their `tiles_XX.tsx` names, layer data
+/// has to be stored as csv.
+pub fn read_tmx(string: &str) -> Result<Vec<(LayerSettings, LevelLayer)>, Error> {
+    let map = serde_xml_rs::from_str::<TmxMap>(string)?;
+
+    let mut tilesets = vec![];
+    for child in map.children.iter() {
+        if let TmxChild::Tileset(tileset) = child {
+            let id = Tileset::id_from_name(&tileset.source).ok_or(Error::InvalidData {
+                info: Some("tile sets have to be named tiles_XX.tsx".to_string()),
+                context: tileset.source.clone(),
+            })?;
+            tilesets.push((tileset.firstgid, id));
+        }
+    }
+    tilesets.sort_by_key(|(first_gid, _)| std::cmp::Reverse(*first_gid));
+
+    let mut layers = vec![];
+    for child in map.children.into_iter() {
+        if let TmxChild::Layer(layer) = child {
+            layers.push(read_layer(layer, &tilesets)?);
+        }
+    }
+    Ok(layers)
+}
+
+fn read_layer(
+    layer: TmxLayer,
+    tilesets: &[(u32, u8)],
+) -> Result<(LayerSettings, LevelLayer), Error> {
+    let invalid_data = |info: String| Error::InvalidData {
+        info: Some(info),
+        context: layer.name.clone(),
+    };
+    if layer.data.encoding.as_deref() != Some("csv") {
+        return Err(invalid_data("layer data has to be csv".to_string()));
+    }
+
+    let property = |name: &str| -> Result<Option<u32>, Error> {
+        layer
+            .properties
+            .iter()
+            .flat_map(|properties| properties.properties.iter())
+            .find(|property| property.name == name)
+            .map(|property| property.value.parse::<u32>())
+            .transpose()
+            .map_err(|err| invalid_data(format!("{}: {}", name, err)))
+    };
+
+    let tiles = layer
+        .data
+        .value
+        .split(',')
+        .map(|gid| gid.trim())
+        .filter(|gid| !gid.is_empty())
+        .map(|gid| {
+            let gid = gid
+                .parse::<u32>()
+                .map_err(|err| invalid_data(format!("{}: {}", gid, err)))?
+                & GID_MASK;
+            if gid == 0 {
+                return Ok(LevelTile::default());
+            }
+            tilesets
+                .iter()
+                .find(|(first_gid, _)| *first_gid <= gid)
+                .and_then(|(first_gid, id)| {
+                    Some(LevelTile {
+                        index: (gid - first_gid).try_into().ok()?,
+                        id: *id,
+                    })
+                })
+                .ok_or_else(|| invalid_data(format!("tile {} is not in any tile set", gid)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let level_layer = LevelLayer {
+        tile_count: property("tile_count")?.unwrap_or(DEFAULT_TILE_COUNT),
+        width: layer.width,
+        height: layer.height,
+        unknown_2: property("unknown_2")?.unwrap_or(0),
+        tiles,
+    };
+    level_layer.validate()?;
+
+    Ok((
+        LayerSettings {
+            name: layer.name,
+            visible: layer.visible.unwrap_or(1) != 0,
+            scroll_speed: (
+                layer.parallaxx.unwrap_or(1.0),
+                layer.parallaxy.unwrap_or(1.0),
+            ),
+        },
+        level_layer,
+    ))
+}
+
+/// Escapes text for use in attributes
+pub(crate) fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[derive(Debug, Deserialize)]
+struct TmxMap {
+    #[serde(rename = "$value", default)]
+    children: Vec<TmxChild>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum TmxChild {
+    Tileset(TmxTileset),
+    Layer(TmxLayer),
+    Properties(IgnoredAny),
+    EditorSettings(IgnoredAny),
+    ObjectGroup(IgnoredAny),
+    ImageLayer(IgnoredAny),
+    Group(IgnoredAny),
+}
+
+#[derive(Debug, Deserialize)]
+struct TmxTileset {
+    firstgid: u32,
+    source: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmxLayer {
+    name: String,
+    width: u32,
+    height: u32,
+    visible: Option<u8>,
+    parallaxx: Option<f32>,
+    parallaxy: Option<f32>,
+    properties: Option<TmxProperties>,
+    data: TmxData,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmxProperties {
+    #[serde(rename = "property", default)]
+    properties: Vec<TmxProperty>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmxProperty {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TmxData {
+    encoding: Option<String>,
+    #[serde(rename = "$value", default)]
+    value: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::media::level::{LevelLayer, LevelTile};
+    use crate::media::tile_collision::TileCollisionMap;
+    use crate::tiled::tmx::{read_tmx, write_tmx};
+    use crate::tiled::tsx::write_tsx;
+    use crate::tiled::{LayerSettings, Tileset};
+
+    fn tileset(id: u8) -> Tileset {
+        Tileset {
+            id,
+            tile_size: 32,
+            image_width: 512,
+            image_height: 512,
+            collision: TileCollisionMap::default(),
+        }
+    }
+
+    fn layers() -> Vec<(LayerSettings, LevelLayer)> {
+        vec![
+            (
+                LayerSettings {
+                    name: "Back & Front".to_string(),
+                    visible: false,
+                    scroll_speed: (0.5, 0.75),
+                },
+                LevelLayer {
+                    tile_count: 256,
+                    width: 3,
+                    height: 1,
+                    unknown_2: 42,
+                    tiles: vec![
+                        LevelTile { index: 0, id: 0 },
+                        LevelTile { index: 5, id: 1 },
+                        LevelTile { index: 255, id: 3 },
+                    ],
+                },
+            ),
+            (
+                LayerSettings {
+                    name: "Main".to_string(),
+                    visible: true,
+                    scroll_speed: (1.0, 1.0),
+                },
+                LevelLayer {
+                    tile_count: 256,
+                    width: 2,
+                    height: 2,
+                    unknown_2: 0,
+                    tiles: vec![
+                        LevelTile { index: 1, id: 3 },
+                        LevelTile { index: 2, id: 1 },
+                        LevelTile { index: 0, id: 0 },
+                        LevelTile { index: 0, id: 1 },
+                    ],
+                },
+            ),
+        ]
+    }
+
+    #[test]
+    fn it_should_round_trip() {
+        let layers = layers();
+        let tmx = write_tmx(&layers, &[tileset(1), tileset(3)]);
+        assert!(tmx.contains("<tileset firstgid=\"257\" source=\"tiles_04.tsx\"/>"));
+        assert!(tmx.contains("name=\"Back &amp; Front\""));
+        assert!(tmx.contains("0,6,512"));
+
+        assert_eq!(read_tmx(&tmx).unwrap(), layers);
+    }
+
+    #[test]
+    fn it_should_round_trip_tile_set_zero() {
+        let wide = Tileset {
+            image_width: 256,
+            image_height: 128,
+            ..tileset(0)
+        };
+        let tsx = write_tsx(&wide);
+        assert!(tsx.contains("tilecount=\"32\" columns=\"8\""));
+
+        let mut layers = layers();
+        layers[0].1.tiles = vec![
+            LevelTile { index: 0, id: 0 },
+            LevelTile { index: 7, id: 0 },
+            LevelTile { index: 5, id: 1 },
+        ];
+        let tmx = write_tmx(&layers, &[wide, tileset(1), tileset(3)]);
+        assert!(tmx.contains("<tileset firstgid=\"33\" source=\"tiles_02.tsx\"/>"));
+        assert!(tmx.contains("0,8,38"));
+
+        assert_eq!(read_tmx(&tmx).unwrap(), layers);
+    }
+
+    #[test]
+    fn it_should_ignore_flip_bits_and_other_layers() {
+        let tmx = write_tmx(&layers()[1..], &[tileset(1), tileset(3)])
+            .replace("258,3,", "2147483906,3,")
+            .replace(
+                " </layer>",
+                " </layer>\n <objectgroup id=\"5\" name=\"Enemies\"><object id=\"1\" x=\"0\" y=\"0\"/></objectgroup>",
+            );
+        assert_eq!(read_tmx(&tmx).unwrap(), layers()[1..]);
+    }
+
+    #[test]
+    fn it_should_reject_unknown_tiles() {
+        let tmx = write_tmx(&layers()[1..], &[tileset(1), tileset(3)]);
+        assert!(read_tmx(&tmx.replace("tiles_02.tsx", "grass.tsx")).is_err());
+        assert!(read_tmx(&tmx.replace("258,3,", "258,3,4,")).is_err());
+    }
+}
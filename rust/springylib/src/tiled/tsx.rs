@@ -0,0 +1,56 @@
+use crate::tiled::tmx::escape;
+use crate::tiled::Tileset;
+
+/// Writes a Tiled tile set
+///
+/// Magenta is keyed out like in the games. Tiles with collision get
+/// their [crate::media::tile_collision::TileFlags] as `collision` property
+/// and the collision polygon as object.
+pub fn write_tsx(tileset: &Tileset) -> String {
+    let tile_size = tileset.tile_size;
+    let name = escape(&tileset.name());
+
+    let mut tsx = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    tsx.push_str(&format!(
+        "<tileset version=\"1.10\" name=\"{}\" tilewidth=\"{}\" tileheight=\"{}\" tilecount=\"{}\" columns=\"{}\">\n",
+        name,
+        tile_size,
+        tile_size,
+        tileset.tile_count(),
+        tileset.columns()
+    ));
+    tsx.push_str(&format!(
+        " <image source=\"{}.bmp\" trans=\"ff00ff\" width=\"{}\" height=\"{}\"/>\n",
+        name, tileset.image_width, tileset.image_height
+    ));
+
+    for (i, flags) in tileset.collision.tiles.iter().enumerate() {
+        if flags.is_empty() || i as u32 >= tileset.tile_count() {
+            continue;
+        }
+
+        tsx.push_str(&format!(" <tile id=\"{}\">\n", i));
+        tsx.push_str("  <properties>\n");
+        tsx.push_str(&format!(
+            "   <property name=\"collision\" type=\"int\" value=\"{}\"/>\n",
+            flags.0
+        ));
+        tsx.push_str("  </properties>\n");
+        if let Some(polygon) = flags.polygon() {
+            let points = polygon
+                .iter()
+                .map(|(x, y)| format!("{},{}", x * tile_size as f32, y * tile_size as f32))
+                .collect::<Vec<_>>()
+                .join(" ");
+            tsx.push_str("  <objectgroup draworder=\"index\">\n");
+            tsx.push_str("   <object id=\"1\" x=\"0\" y=\"0\">\n");
+            tsx.push_str(&format!("    <polygon points=\"{}\"/>\n", points));
+            tsx.push_str("   </object>\n");
+            tsx.push_str("  </objectgroup>\n");
+        }
+        tsx.push_str(" </tile>\n");
+    }
+
+    tsx.push_str("</tileset>\n");
+    tsx
+}
@@ -0,0 +1,77 @@
+use crate::archive::path::normalize;
+use crate::vfs::{ReadSeek, VfsSource};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+/// Loose files on disk, such as a mod folder
+///
+/// The folder mirrors the datafile, so `<root>/data/foo.bmp` overrides
+/// `data\\foo.bmp`. Lookups ignore case even on case-sensitive file systems.
+pub struct Directory {
+    root: PathBuf,
+    /// normalized path to path on disk
+    files: HashMap<String, PathBuf>,
+}
+
+impl Directory {
+    pub fn new<P>(root: P) -> std::io::Result<Directory>
+    where
+        P: AsRef<Path>,
+    {
+        let mut directory = Directory {
+            root: root.as_ref().to_path_buf(),
+            files: HashMap::new(),
+        };
+        directory.refresh()?;
+        Ok(directory)
+    }
+
+    /// Rescans the folder for added or removed files
+    ///
+    /// Symlinked directories are skipped, so links pointing back up
+    /// the tree can't send the scan in circles. Symlinked files are kept.
+    pub fn refresh(&mut self) -> std::io::Result<()> {
+        self.files.clear();
+        let mut stack = vec![self.root.clone()];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(dir)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                let path = entry.path();
+                if file_type.is_dir() {
+                    stack.push(path);
+                } else if file_type.is_symlink() && path.is_dir() {
+                    continue;
+                } else if let Ok(relative) = path.strip_prefix(&self.root) {
+                    self.files
+                        .insert(normalize(&relative.to_string_lossy()), path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl VfsSource for Directory {
+    fn files(&self) -> Vec<String> {
+        self.files.keys().cloned().collect()
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn open(&mut self, path: &str) -> std::io::Result<Box<dyn ReadSeek + '_>> {
+        let path = self
+            .files
+            .get(path)
+            .ok_or(std::io::Error::new(ErrorKind::NotFound, path))?;
+        Ok(Box::new(File::open(path)?))
+    }
+}
@@ -0,0 +1,190 @@
+use crate::archive::path::normalize;
+use crate::archive::reader::ArchiveReader;
+use crate::error::Error;
+use crate::{load_file, DatafileFile};
+use std::collections::BTreeSet;
+use std::io::{ErrorKind, Read, Seek};
+
+pub mod directory;
+#[cfg(feature = "sarc")]
+pub mod sarc;
+
+pub trait ReadSeek: Read + Seek {}
+
+impl<T> ReadSeek for T where T: Read + Seek {}
+
+/// Anything that can be mounted into a [Vfs]
+///
+/// All paths passed to and returned from a source are normalized,
+/// see [normalize].
+pub trait VfsSource {
+    /// All files in this source
+    fn files(&self) -> Vec<String>;
+
+    fn contains(&self, path: &str) -> bool;
+
+    fn open(&mut self, path: &str) -> std::io::Result<Box<dyn ReadSeek + '_>>;
+
+    /// Archive header name, if this source has one
+    fn name(&self) -> Option<&str> {
+        None
+    }
+}
+
+/// Layered file system over multiple sources
+///
+/// Sources that are mounted later take precedence, so a patch archive
+/// or a mod folder can override single files of the original datafile.
+#[derive(Default)]
+pub struct Vfs {
+    layers: Vec<Box<dyn VfsSource>>,
+}
+
+impl Vfs {
+    pub fn new() -> Vfs {
+        Vfs::default()
+    }
+
+    /// Mounts a source on top of all previously mounted ones
+    pub fn mount<S>(&mut self, source: S)
+    where
+        S: VfsSource + 'static,
+    {
+        self.layers.push(Box::new(source));
+    }
+
+    /// Index of the layer a path resolves to, counting in mount order
+    pub fn layer_of(&self, path: &str) -> Option<usize> {
+        let path = normalize(path);
+        self.layers
+            .iter()
+            .rposition(|layer| layer.contains(path.as_str()))
+    }
+
+    /// Header name of the first mounted source that has one,
+    /// usually the datafile of the game
+    pub fn name(&self) -> Option<&str> {
+        self.layers.iter().find_map(|layer| layer.name())
+    }
+
+    pub fn exists(&self, path: &str) -> bool {
+        self.layer_of(path).is_some()
+    }
+
+    /// All files of all layers
+    pub fn files(&self) -> BTreeSet<String> {
+        self.layers.iter().flat_map(|layer| layer.files()).collect()
+    }
+
+    /// Opens a file from the highest priority layer that contains it
+    pub fn open(&mut self, path: &str) -> std::io::Result<Box<dyn ReadSeek + '_>> {
+        let layer = self
+            .layer_of(path)
+            .ok_or(std::io::Error::new(ErrorKind::NotFound, path))?;
+        self.layers[layer].open(normalize(path).as_str())
+    }
+
+    pub fn read(&mut self, path: &str) -> std::io::Result<Vec<u8>> {
+        let mut data = vec![];
+        self.open(path)?.read_to_end(&mut data)?;
+        Ok(data)
+    }
+
+    /// Loads and decodes a file
+    pub fn load(&mut self, path: &str) -> Result<DatafileFile, Error> {
+        if !self.exists(path) {
+            return Err(Error::InvalidPath(path.to_string()));
+        }
+        let path = normalize(path);
+        load_file(path.as_str(), &mut self.open(path.as_str())?)
+    }
+}
+
+impl<R> VfsSource for ArchiveReader<R>
+where
+    R: Read + Seek,
+{
+    fn files(&self) -> Vec<String> {
+        self.keys().map(|path| normalize(path)).collect()
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.find(path).is_some()
+    }
+
+    fn open(&mut self, path: &str) -> std::io::Result<Box<dyn ReadSeek + '_>> {
+        Ok(Box::new(ArchiveReader::open(self, path)?))
+    }
+
+    fn name(&self) -> Option<&str> {
+        Some(self.archive().name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::archive::reader::ArchiveReader;
+    use crate::archive::writer::ArchiveWriter;
+    use crate::archive::ArchiveKind;
+    use crate::vfs::directory::Directory;
+    use crate::vfs::{Vfs, VfsSource};
+    use std::fs;
+    use std::io::{Cursor, Seek};
+
+    fn archive(files: &[(&str, &[u8])]) -> ArchiveReader<Cursor<Vec<u8>>> {
+        let mut writer = ArchiveWriter::new(ArchiveKind::V2, "MHJNR-XXL");
+        for (path, data) in files {
+            writer.add_file(path, data.to_vec()).unwrap();
+        }
+        let mut out = Cursor::new(Vec::new());
+        writer.write(&mut out).unwrap();
+        out.rewind().unwrap();
+        ArchiveReader::new(out).unwrap()
+    }
+
+    #[test]
+    fn it_should_override_files() {
+        let mut vfs = Vfs::new();
+        vfs.mount(archive(&[
+            ("data\\a.txt", b"original a"),
+            ("data\\b.txt", b"original b"),
+        ]));
+        vfs.mount(archive(&[("data\\B.txt", b"patched b")]));
+
+        assert_eq!(vfs.read("data/a.txt").unwrap(), b"original a");
+        assert_eq!(vfs.read("data\\b.txt").unwrap(), b"patched b");
+        assert_eq!(vfs.layer_of("data/b.txt"), Some(1));
+        assert_eq!(vfs.name(), Some("MHJNR-XXL"));
+        assert!(vfs.read("data/c.txt").is_err());
+        assert_eq!(vfs.files().len(), 2);
+    }
+
+    #[test]
+    fn it_should_mount_directories() {
+        let root = std::env::temp_dir().join(format!("springylib-vfs-{}", std::process::id()));
+        fs::create_dir_all(root.join("Data").join("sprites")).unwrap();
+        fs::write(root.join("Data").join("sprites").join("Hero.bmp"), b"mod").unwrap();
+
+        let mut vfs = Vfs::new();
+        vfs.mount(archive(&[("data\\sprites\\hero.bmp", b"original")]));
+        vfs.mount(Directory::new(&root).unwrap());
+
+        assert_eq!(vfs.read("data/sprites/hero.bmp").unwrap(), b"mod");
+        fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn it_should_skip_symlinked_directories() {
+        let root = std::env::temp_dir().join(format!("springylib-vfs-link-{}", std::process::id()));
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("data").join("a.txt"), b"a").unwrap();
+        std::os::unix::fs::symlink(&root, root.join("data").join("loop")).unwrap();
+        std::os::unix::fs::symlink(root.join("data").join("a.txt"), root.join("b.txt")).unwrap();
+
+        let mut files = Directory::new(&root).unwrap().files();
+        files.sort();
+        assert_eq!(files, vec!["b.txt", "data/a.txt"]);
+        fs::remove_dir_all(root).unwrap();
+    }
+}
@@ -0,0 +1,125 @@
+use crate::archive::path::normalize;
+use crate::archive::reader::EntryReader;
+use crate::archive::FilePointer;
+use crate::vfs::{ReadSeek, VfsSource};
+use starforcelib::sarc::SarcArchive;
+use std::collections::HashMap;
+use std::io::{ErrorKind, Read, Seek};
+
+/// SARC archive from the Starforce engine
+pub struct SarcSource<R>
+where
+    R: Read + Seek,
+{
+    reader: R,
+    /// normalized path to absolute position
+    files: HashMap<String, FilePointer>,
+}
+
+impl<R> SarcSource<R>
+where
+    R: Read + Seek,
+{
+    pub fn new(mut reader: R) -> std::io::Result<SarcSource<R>> {
+        let archive = SarcArchive::read(&mut reader)
+            .map_err(|err| std::io::Error::new(ErrorKind::InvalidData, err))?;
+        let offset = archive.position.pos as usize;
+        let files = archive
+            .files
+            .into_iter()
+            .map(|file| {
+                (
+                    normalize(&file.path),
+                    FilePointer {
+                        position: offset + file.position as usize,
+                        length: file.size as usize,
+                        path: file.path,
+                    },
+                )
+            })
+            .collect();
+
+        Ok(SarcSource { reader, files })
+    }
+}
+
+impl<R> VfsSource for SarcSource<R>
+where
+    R: Read + Seek,
+{
+    fn files(&self) -> Vec<String> {
+        self.files.keys().cloned().collect()
+    }
+
+    fn contains(&self, path: &str) -> bool {
+        self.files.contains_key(path)
+    }
+
+    fn open(&mut self, path: &str) -> std::io::Result<Box<dyn ReadSeek + '_>> {
+        let pointer = self
+            .files
+            .get(path)
+            .ok_or(std::io::Error::new(ErrorKind::NotFound, path))?;
+        Ok(Box::new(EntryReader::new(&mut self.reader, pointer)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::vfs::sarc::SarcSource;
+    use crate::vfs::{Vfs, VfsSource};
+    use std::io::{Cursor, Read};
+
+    /// Builds the file by hand, the writer of [starforcelib] skips the padding after paths
+    fn sarc(files: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+        let mut out = b"SARC".to_vec();
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&(files.len() as u32).to_le_bytes());
+        let mut position = 0u32;
+        for (path, data) in files {
+            out.push(path.len() as u8);
+            out.extend_from_slice(path.as_bytes());
+            out.push(0);
+            out.extend_from_slice(&position.to_le_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+            position += data.len() as u32;
+        }
+        for (_, data) in files {
+            out.extend_from_slice(data);
+        }
+        Cursor::new(out)
+    }
+
+    #[test]
+    fn it_should_read_files() {
+        let mut source = SarcSource::new(sarc(&[
+            ("Data\\Hero.txt", b"hero"),
+            ("data\\level.txt", b"level 1"),
+        ]))
+        .unwrap();
+
+        let mut files = source.files();
+        files.sort();
+        assert_eq!(files, vec!["data/hero.txt", "data/level.txt"]);
+        assert!(source.contains("data/hero.txt"));
+        assert!(!source.contains("data/villain.txt"));
+
+        let mut data = String::new();
+        source
+            .open("data/level.txt")
+            .unwrap()
+            .read_to_string(&mut data)
+            .unwrap();
+        assert_eq!(data, "level 1");
+        assert!(source.open("data/villain.txt").is_err());
+    }
+
+    #[test]
+    fn it_should_mount() {
+        let mut vfs = Vfs::new();
+        vfs.mount(SarcSource::new(sarc(&[("data\\a.txt", b"a")])).unwrap());
+
+        assert_eq!(vfs.read("DATA/A.TXT").unwrap(), b"a");
+    }
+}
@@ -1,9 +1,11 @@
 use binrw::prelude::*;
 use binrw::{BinRead, PosValue};
+use std::collections::HashSet;
 use std::fmt::{Debug, Formatter};
 use std::fs;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, SeekFrom, Write};
+use std::ops::Range;
 use std::path::Path;
 
 #[binrw]
@@ -36,6 +38,32 @@ pub struct FilePointer {
     pub size_2: u32,
 }
 
+/// Result of an integrity check, all offsets are absolute
+///
+/// Same fields as the report of springylib's `Archive::verify`, with
+/// entries ordered the same way: by position, then size and path, and
+/// duplicates sorted by path.
+#[derive(Debug, Default, PartialEq)]
+pub struct VerifyReport {
+    /// Entries that don't fit into the file
+    pub out_of_bounds: Vec<String>,
+    /// Pairs of entries that share at least one byte
+    pub overlaps: Vec<(String, String)>,
+    /// Paths that appear more than once, sorted
+    pub duplicates: Vec<String>,
+    /// Ranges that are not referenced by any entry, but contain data
+    pub unreferenced: Vec<Range<usize>>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.out_of_bounds.is_empty()
+            && self.overlaps.is_empty()
+            && self.duplicates.is_empty()
+            && self.unreferenced.is_empty()
+    }
+}
+
 impl std::fmt::Display for SarcArchive {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         writeln!(f, "v{}", self.version)?;
@@ -81,6 +109,59 @@ impl SarcArchive {
             .and_then(|ptr| ptr.extract(file, self.position.pos))
     }
 
+    /// Checks the file table against the contents of `file`
+    pub fn verify<R>(&self, file: &mut R) -> std::io::Result<VerifyReport>
+    where
+        R: Read + Seek,
+    {
+        let length = file.seek(SeekFrom::End(0))? as usize;
+        let offset = self.position.pos as usize;
+        let mut files: Vec<&FilePointer> = self.files.iter().collect();
+        files.sort_by_key(|ptr| (ptr.position, ptr.size, &ptr.path));
+
+        let mut report = VerifyReport::default();
+        let mut seen = HashSet::new();
+        for ptr in self.files.iter() {
+            if !seen.insert(ptr.path.as_str()) && !report.duplicates.contains(&ptr.path) {
+                report.duplicates.push(ptr.path.clone());
+            }
+        }
+        report.duplicates.sort();
+
+        let mut covered = offset;
+        let mut last: Option<&FilePointer> = None;
+        for ptr in files {
+            let start = offset + ptr.position as usize;
+            let end = start + ptr.size as usize;
+            if end > length {
+                report.out_of_bounds.push(ptr.path.clone());
+            }
+            if ptr.size == 0 {
+                continue;
+            }
+
+            if let Some(last) = last.filter(|_| start < covered) {
+                report.overlaps.push((last.path.clone(), ptr.path.clone()));
+            } else if start > covered {
+                let gap = covered..start.min(length);
+                if contains_data(file, gap.clone())? {
+                    report.unreferenced.push(gap);
+                }
+            }
+
+            if end > covered {
+                covered = end;
+                last = Some(ptr);
+            }
+        }
+
+        if covered < length && contains_data(file, covered..length)? {
+            report.unreferenced.push(covered..length);
+        }
+
+        Ok(report)
+    }
+
     pub fn extract_all(path: &str) -> std::io::Result<()> {
         let info = SarcArchive::read_file(path)?;
         let mut file = File::open(path)?;
@@ -119,3 +200,94 @@ impl FilePointer {
         Ok(data)
     }
 }
+
+/// Checks if there is anything but padding in the range
+fn contains_data<R>(file: &mut R, range: Range<usize>) -> std::io::Result<bool>
+where
+    R: Read + Seek,
+{
+    if range.is_empty() {
+        return Ok(false);
+    }
+
+    file.seek(SeekFrom::Start(range.start as u64))?;
+    let mut remaining = range.len();
+    let mut buffer = [0u8; 0x1000];
+    while remaining > 0 {
+        let chunk = remaining.min(buffer.len());
+        file.read_exact(&mut buffer[..chunk])?;
+        if buffer[..chunk].iter().any(|byte| *byte != 0) {
+            return Ok(true);
+        }
+        remaining -= chunk;
+    }
+
+    Ok(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sarc::{FilePointer, SarcArchive};
+    use binrw::{BinWrite, PosValue};
+    use std::io::Cursor;
+
+    fn archive(files: &[(&str, u32, u32)]) -> (SarcArchive, Vec<u8>) {
+        let mut archive = SarcArchive {
+            version: 1,
+            files: files
+                .iter()
+                .map(|(path, position, size)| FilePointer {
+                    path: path.to_string(),
+                    position: *position,
+                    size: *size,
+                })
+                .collect(),
+            position: PosValue { val: (), pos: 0 },
+        };
+        let mut data = Cursor::new(Vec::new());
+        archive.write(&mut data).unwrap();
+        archive.position.pos = data.get_ref().len() as u64;
+        (archive, data.into_inner())
+    }
+
+    #[test]
+    fn it_should_verify() {
+        let (archive, mut data) = archive(&[("a", 0, 4), ("b", 4, 4)]);
+        data.extend_from_slice(&[1; 8]);
+        let report = archive.verify(&mut Cursor::new(data)).unwrap();
+        assert!(report.is_ok(), "{:?}", report);
+    }
+
+    #[test]
+    fn it_should_report_problems() {
+        let (archive, mut data) = archive(&[("a", 0, 4), ("b", 2, 4), ("a", 12, 8)]);
+        let offset = data.len();
+        data.extend_from_slice(&[1; 16]);
+        let report = archive.verify(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(report.out_of_bounds, vec!["a".to_string()]);
+        assert_eq!(report.overlaps, vec![("a".to_string(), "b".to_string())]);
+        assert_eq!(report.duplicates, vec!["a".to_string()]);
+        assert_eq!(report.unreferenced, vec![offset + 6..offset + 12]);
+    }
+
+    #[test]
+    fn it_should_sort_duplicates() {
+        let (archive, mut data) = archive(&[("b", 0, 1), ("a", 1, 1), ("b", 2, 1), ("a", 3, 1)]);
+        data.extend_from_slice(&[1; 4]);
+        let report = archive.verify(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(report.duplicates, vec!["a".to_string(), "b".to_string()]);
+    }
+
+    #[test]
+    fn it_should_find_data_in_large_gaps() {
+        let (archive, mut data) = archive(&[("a", 0x3000, 4)]);
+        let offset = data.len();
+        data.resize(offset + 0x3004, 0);
+        data[offset + 0x2001] = 1;
+        let report = archive.verify(&mut Cursor::new(data)).unwrap();
+
+        assert_eq!(report.unreferenced, vec![offset..offset + 0x3000]);
+    }
+}